@@ -0,0 +1,207 @@
+//! Listing audio devices and switching the default one through the Core Audio APIs
+
+use std::io;
+
+/// An active playback or recording device
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    pub playback: bool,
+    pub is_default: bool,
+}
+
+// Windows has no public API for changing the default device, but this interface has been used
+// by its own sound settings since Windows 7
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod policy_config {
+    use std::ffi::c_void;
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::ntdef::LPCWSTR;
+    use winapi::shared::winerror::HRESULT;
+    use winapi::um::mmdeviceapi::ERole;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::RIDL;
+
+    RIDL! {#[uuid(0xf8679f50, 0x850a, 0x41cf, 0x9c, 0x72, 0x43, 0x0f, 0x29, 0x02, 0x90, 0xc8)]
+    interface IPolicyConfig(IPolicyConfigVtbl): IUnknown(IUnknownVtbl) {
+        fn GetMixFormat(device: LPCWSTR, format: *mut c_void,) -> HRESULT,
+        fn GetDeviceFormat(device: LPCWSTR, default: i32, format: *mut c_void,) -> HRESULT,
+        fn ResetDeviceFormat(device: LPCWSTR,) -> HRESULT,
+        fn SetDeviceFormat(device: LPCWSTR, endpoint: *mut c_void, mix: *mut c_void,) -> HRESULT,
+        fn GetProcessingPeriod(
+            device: LPCWSTR,
+            default: i32,
+            period: *mut i64,
+            minimum: *mut i64,
+        ) -> HRESULT,
+        fn SetProcessingPeriod(device: LPCWSTR, period: *mut i64,) -> HRESULT,
+        fn GetShareMode(device: LPCWSTR, mode: *mut c_void,) -> HRESULT,
+        fn SetShareMode(device: LPCWSTR, mode: *mut c_void,) -> HRESULT,
+        fn GetPropertyValue(device: LPCWSTR, key: *const c_void, value: *mut c_void,) -> HRESULT,
+        fn SetPropertyValue(device: LPCWSTR, key: *const c_void, value: *mut c_void,) -> HRESULT,
+        fn SetDefaultEndpoint(device: LPCWSTR, role: ERole,) -> HRESULT,
+        fn SetEndpointVisibility(device: LPCWSTR, visible: i32,) -> HRESULT,
+    }}
+
+    pub const CLSID_POLICY_CONFIG_CLIENT: GUID = GUID {
+        Data1: 0x870a_f99c,
+        Data2: 0x171d,
+        Data3: 0x4f9e,
+        Data4: [0xaf, 0x0d, 0xe6, 0x3d, 0xf4, 0x0c, 0x2b, 0xc9],
+    };
+}
+
+#[cfg(windows)]
+fn check(result: winapi::shared::winerror::HRESULT) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+unsafe fn device_id(device: *mut winapi::um::mmdeviceapi::IMMDevice) -> io::Result<String> {
+    use crate::win::from_wide_ptr;
+    use std::ptr;
+    use winapi::um::combaseapi::CoTaskMemFree;
+
+    let mut id = ptr::null_mut();
+    check((*device).GetId(&mut id))?;
+    let result = from_wide_ptr(id);
+    CoTaskMemFree(id as *mut _);
+    Ok(result)
+}
+
+#[cfg(windows)]
+unsafe fn device_name(device: *mut winapi::um::mmdeviceapi::IMMDevice) -> io::Result<String> {
+    use crate::win::from_wide_ptr;
+    use std::{mem, ptr};
+    use winapi::um::combaseapi::PropVariantClear;
+    use winapi::um::coml2api::STGM_READ;
+    use winapi::um::functiondiscoverykeys_devpkey::PKEY_Device_FriendlyName;
+
+    let mut store = ptr::null_mut();
+    check((*device).OpenPropertyStore(STGM_READ, &mut store))?;
+    let mut value = mem::zeroed();
+    let result = check((*store).GetValue(&PKEY_Device_FriendlyName, &mut value));
+    let name = result.map(|()| {
+        let name = *value.data.pwszVal();
+        if name.is_null() {
+            String::new()
+        } else {
+            from_wide_ptr(name)
+        }
+    });
+    PropVariantClear(&mut value);
+    (*store).Release();
+    name
+}
+
+/// Active playback devices followed by recording devices
+#[cfg(windows)]
+pub fn list() -> io::Result<Vec<Device>> {
+    use crate::win::init_com;
+    use std::ptr;
+    use winapi::um::combaseapi::{CoCreateInstance, CLSCTX_ALL};
+    use winapi::um::mmdeviceapi::{
+        eCapture, eConsole, eRender, CLSID_MMDeviceEnumerator, IMMDeviceEnumerator,
+        DEVICE_STATE_ACTIVE,
+    };
+    use winapi::Interface;
+
+    init_com();
+    let mut devices = Vec::new();
+    unsafe {
+        let mut enumerator: *mut IMMDeviceEnumerator = ptr::null_mut();
+        check(CoCreateInstance(
+            &CLSID_MMDeviceEnumerator,
+            ptr::null_mut(),
+            CLSCTX_ALL,
+            &IMMDeviceEnumerator::uuidof(),
+            &mut enumerator as *mut *mut IMMDeviceEnumerator as *mut _,
+        ))?;
+
+        for flow in [eRender, eCapture] {
+            // There's no default device when none of them are plugged in
+            let mut default_id = None;
+            let mut default = ptr::null_mut();
+            if (*enumerator).GetDefaultAudioEndpoint(flow, eConsole, &mut default) >= 0 {
+                default_id = device_id(default).ok();
+                (*default).Release();
+            }
+
+            let mut collection = ptr::null_mut();
+            check((*enumerator).EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE, &mut collection))?;
+            // winapi declares the count as `*const`, though it's written to
+            let mut count: u32 = 0;
+            check((*collection).GetCount(ptr::addr_of_mut!(count)))?;
+            for i in 0..count {
+                let mut device = ptr::null_mut();
+                if (*collection).Item(i, &mut device) < 0 {
+                    continue;
+                }
+                if let (Ok(id), Ok(name)) = (device_id(device), device_name(device)) {
+                    devices.push(Device {
+                        is_default: default_id.as_ref() == Some(&id),
+                        id,
+                        name,
+                        playback: flow == eRender,
+                    });
+                }
+                (*device).Release();
+            }
+            (*collection).Release();
+        }
+        (*enumerator).Release();
+    }
+    Ok(devices)
+}
+
+/// Make the device the default for all roles: games and system sounds, media and calls
+#[cfg(windows)]
+pub fn set_default(id: &str) -> io::Result<()> {
+    use crate::win::{init_com, to_wide};
+    use policy_config::{IPolicyConfig, CLSID_POLICY_CONFIG_CLIENT};
+    use std::ptr;
+    use winapi::um::combaseapi::{CoCreateInstance, CLSCTX_ALL};
+    use winapi::um::mmdeviceapi::{eCommunications, eConsole, eMultimedia};
+    use winapi::Interface;
+
+    init_com();
+    let id = to_wide(id);
+    unsafe {
+        let mut policy_config: *mut IPolicyConfig = ptr::null_mut();
+        check(CoCreateInstance(
+            &CLSID_POLICY_CONFIG_CLIENT,
+            ptr::null_mut(),
+            CLSCTX_ALL,
+            &IPolicyConfig::uuidof(),
+            &mut policy_config as *mut *mut IPolicyConfig as *mut _,
+        ))?;
+        let mut result = Ok(());
+        for role in [eConsole, eMultimedia, eCommunications] {
+            result = result.and(check(
+                (*policy_config).SetDefaultEndpoint(id.as_ptr(), role),
+            ));
+        }
+        (*policy_config).Release();
+        result
+    }
+}
+
+#[cfg(not(windows))]
+pub fn list() -> io::Result<Vec<Device>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Audio devices are only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn set_default(_id: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Audio devices are only supported on Windows",
+    ))
+}
@@ -0,0 +1,49 @@
+//! Starting wlines_run at login through the Run key of the current user
+
+use std::io;
+
+#[cfg(windows)]
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+#[cfg(windows)]
+const VALUE_NAME: &str = "wlines_run";
+
+/// Run `command_line` at login, replacing what was registered before
+#[cfg(windows)]
+pub fn install(command_line: &str) -> io::Result<()> {
+    use crate::registry::{set_string, HKEY_CURRENT_USER};
+    set_string(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME, command_line)
+}
+
+#[cfg(windows)]
+pub fn remove() -> io::Result<()> {
+    use crate::registry::{delete_value, HKEY_CURRENT_USER};
+    delete_value(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME)
+}
+
+/// The registered command line, if any
+#[cfg(windows)]
+pub fn installed() -> Option<String> {
+    use crate::registry::{get_string, HKEY_CURRENT_USER};
+    get_string(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME)
+}
+
+#[cfg(not(windows))]
+pub fn install(_command_line: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Autostart is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn remove() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Autostart is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn installed() -> Option<String> {
+    None
+}
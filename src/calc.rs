@@ -0,0 +1,226 @@
+//! Evaluates arithmetic typed into the menu, e.g. `=2^10 / 4` or `(1.5 + 2) * 3`
+
+/// Whether input without a `=` prefix looks like arithmetic rather than a search
+pub fn looks_like_expression(input: &str) -> bool {
+    input.chars().any(|c| c.is_ascii_digit())
+        && input.chars().any(|c| "+-*/%^".contains(c))
+        && input
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_whitespace() || ".,+-*/%^()".contains(c))
+}
+
+/// Evaluate an expression with `+ - * / % ^`, parentheses, `pi`, `e` and a few functions
+pub fn evaluate(expression: &str) -> Option<f64> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expression()?;
+    if parser.pos != parser.tokens.len() || !value.is_finite() {
+        return None;
+    }
+    Some(value)
+}
+
+/// Format a result without float noise, e.g. `0.30000000000000004` as `0.3`
+pub fn format_number(value: f64) -> String {
+    if value == value.trunc() && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let formatted = format!("{:.10}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' {
+                    number.push(c);
+                } else if c != ',' {
+                    // `,` is only a thousands separator
+                    break;
+                }
+                chars.next();
+            }
+            tokens.push(Token::Number(number.parse().ok()?));
+        } else if c.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                name.push(c.to_ascii_lowercase());
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else {
+            tokens.push(match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                '+' | '-' | '*' | '/' | '%' | '^' => Token::Operator(c),
+                '×' => Token::Operator('*'),
+                '÷' => Token::Operator('/'),
+                _ => return None,
+            });
+            chars.next();
+        }
+    }
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_is_operator(&mut self, operators: &str) -> Option<char> {
+        match self.peek() {
+            Some(Token::Operator(c)) if operators.contains(*c) => {
+                let c = *c;
+                self.pos += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    // expression = term (('+' | '-') term)*
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(operator) = self.next_is_operator("+-") {
+            let rhs = self.term()?;
+            value = if operator == '+' {
+                value + rhs
+            } else {
+                value - rhs
+            };
+        }
+        Some(value)
+    }
+
+    // term = unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(operator) = self.next_is_operator("*/%") {
+            let rhs = self.unary()?;
+            value = match operator {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    // unary = '-' unary | power
+    fn unary(&mut self) -> Option<f64> {
+        if self.next_is_operator("-").is_some() {
+            return Some(-self.unary()?);
+        }
+        if self.next_is_operator("+").is_some() {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    // power = atom ('^' unary)?, which makes `2^3^2` right-associative
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        if self.next_is_operator("^").is_some() {
+            return Some(base.powf(self.unary()?));
+        }
+        Some(base)
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        let token = self.peek()?.clone();
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Some(value),
+            Token::Open => {
+                let value = self.expression()?;
+                if self.peek() != Some(&Token::Close) {
+                    return None;
+                }
+                self.pos += 1;
+                Some(value)
+            }
+            Token::Name(name) => match name.as_str() {
+                "pi" => Some(std::f64::consts::PI),
+                "e" => Some(std::f64::consts::E),
+                _ => {
+                    let function: fn(f64) -> f64 = match name.as_str() {
+                        "sqrt" => f64::sqrt,
+                        "abs" => f64::abs,
+                        "sin" => f64::sin,
+                        "cos" => f64::cos,
+                        "tan" => f64::tan,
+                        "ln" => f64::ln,
+                        "log" => f64::log10,
+                        "round" => f64::round,
+                        "floor" => f64::floor,
+                        "ceil" => f64::ceil,
+                        _ => return None,
+                    };
+                    Some(function(self.atom()?))
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(evaluate("1 + 2 * 3"), Some(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(evaluate("2^3^2"), Some(512.0));
+        assert_eq!(evaluate("-2^2"), Some(-4.0));
+        assert_eq!(evaluate("10 % 4"), Some(2.0));
+        assert_eq!(evaluate("sqrt(16) + 1,000"), Some(1004.0));
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert_eq!(evaluate("1 +"), None);
+        assert_eq!(evaluate("(1 + 2"), None);
+        assert_eq!(evaluate("1 / 0"), None);
+        assert_eq!(evaluate("firefox"), None);
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(42.0), "42");
+        assert_eq!(format_number(-1.5), "-1.5");
+    }
+
+    #[test]
+    fn expression_detection() {
+        assert!(looks_like_expression("12 * 4"));
+        assert!(!looks_like_expression("7-zip"));
+        assert!(!looks_like_expression("1234"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Command line interface. Arguments that aren't wlines_run's own are passed on to wlines.
+
+use clap::{Args, Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(version, about = "A program launcher for wlines")]
+pub struct Cli {
+    /// The wlines executable to use instead of the one in PATH
+    #[arg(long, global = true, value_name = "PATH")]
+    pub wlines_path: Option<String>,
+    /// Keep the index, history and configuration next to the executable instead of in %AppData%
+    #[arg(long, global = true)]
+    pub portable: bool,
+    /// Don't write the index, history or any other data file
+    #[arg(long, global = true)]
+    pub read_only: bool,
+    /// The index file to use instead of the one in the data folder
+    #[arg(long, global = true, value_name = "PATH")]
+    pub index_file: Option<String>,
+    /// The history file to use instead of the active profile's
+    #[arg(long, global = true, value_name = "PATH")]
+    pub history_file: Option<String>,
+    /// Also log debug messages
+    #[arg(long, global = true)]
+    pub verbose: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Arguments passed on to wlines, after wlines_run's own options
+#[derive(Args)]
+pub struct WlinesArgs {
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "WLINES_ARGS"
+    )]
+    pub wlines_args: Vec<String>,
+}
+
+/// Options of the menus that launch programs
+#[derive(Args)]
+pub struct RunArgs {
+    /// Print what would be launched instead of launching it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Rank by and record launches in another history file
+    #[arg(long, value_name = "PATH")]
+    pub history: Option<String>,
+    /// Don't record anything in the history
+    #[arg(long)]
+    pub no_history: bool,
+    /// Rebuild the index before showing the menu
+    #[arg(long)]
+    pub reindex: bool,
+    #[command(flatten)]
+    pub wlines: WlinesArgs,
+}
+
+/// Options of the pickers that insert text
+#[derive(Args)]
+pub struct TypeArgs {
+    /// Type the text into the focused window instead of copying it
+    #[arg(long = "type")]
+    pub type_it: bool,
+    #[command(flatten)]
+    pub wlines: WlinesArgs,
+}
+
+/// Arguments of a `menu:<name>` subcommand
+#[derive(Parser)]
+pub struct CustomMenuArgs {
+    /// Print the command line instead of running it
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(flatten)]
+    pub wlines: WlinesArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Index the programs of the Start Menu, PATH and `index_dirs`
+    Index,
+    /// Check that wlines can be found, create a configuration and build the first index
+    Setup(WlinesArgs),
+    /// Show the menu of programs and launch the chosen one
+    Run(RunArgs),
+    /// Keep the menu ready in the background to show it quicker with `show`
+    Daemon,
+    /// Show the menu of the running daemon, or like `run` if there's none
+    Show(WlinesArgs),
+    /// Open a file with the program chosen from the menu
+    Open {
+        file: String,
+        #[command(flatten)]
+        run: RunArgs,
+    },
+    /// Launch a program by path or title without showing a menu
+    Launch {
+        /// Print what would be launched instead of launching it
+        #[arg(long)]
+        dry_run: bool,
+        /// Run the program as administrator
+        #[arg(long)]
+        admin: bool,
+        /// Record the launch in another history file
+        #[arg(long, value_name = "PATH")]
+        history: Option<String>,
+        /// Don't record the launch in the history
+        #[arg(long)]
+        no_history: bool,
+        /// Shell verb to launch the program with
+        #[arg(long)]
+        verb: Option<String>,
+        target: String,
+        /// Arguments passed to the program
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Print the path of the best ranked program matching the query
+    Which {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Choose from a list that is read from stdin, ranked by a history of its own
+    Menu {
+        name: String,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Kill a running process
+    Kill(WlinesArgs),
+    /// Shut down, restart, sleep, lock or sign out
+    Power(WlinesArgs),
+    /// Switch to another virtual desktop
+    Desktops {
+        /// Move the focused window to the desktop instead
+        #[arg(long = "move")]
+        move_window: bool,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Copy a password from the `password_manager`
+    Pass {
+        /// Clear the clipboard after some seconds if it still holds the password
+        #[arg(long, num_args = 2, value_names = ["SEQUENCE_NUMBER", "SECONDS"], hide = true)]
+        clear: Option<Vec<u64>>,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Switch the projection mode or a display preset
+    Display(WlinesArgs),
+    /// Connect to a wireless network
+    Wifi(WlinesArgs),
+    /// Connect to or disconnect from a VPN
+    Vpn(WlinesArgs),
+    /// Start a timer like `25m` with a message, or list the pending timers to cancel one
+    Timer {
+        /// Wait for a timer in the background, until the given Unix time
+        #[arg(long, value_name = "DUE", hide = true)]
+        wait: Option<u64>,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS"
+        )]
+        args: Vec<String>,
+    },
+    /// Switch the default audio device
+    Audio(WlinesArgs),
+    /// Start, stop or restart a Windows service
+    Services(WlinesArgs),
+    /// Copy the value of an environment variable
+    Env {
+        /// Show the entries of lists like PATH one per line
+        #[arg(long)]
+        split: bool,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Search for a package with winget and install the chosen one
+    Winget {
+        query: String,
+        /// Print the install command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Copy a clip from the clipboard history
+    Clip {
+        /// Record the clipboard history in the background
+        #[arg(long)]
+        watch: bool,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Copy or type an emoji
+    Emoji(TypeArgs),
+    /// Copy or type a symbol
+    Symbol(TypeArgs),
+    /// Copy or type one of the configured `snippets`
+    Snippet(TypeArgs),
+    /// Append a note to the notes file
+    Note(WlinesArgs),
+    /// Copy or type one of the notes
+    Notes(TypeArgs),
+    /// Open a recently used directory
+    #[command(args_conflicts_with_subcommands = true)]
+    Dirs {
+        #[command(subcommand)]
+        action: Option<DirsCommand>,
+        /// Open the directory in the `directory_terminal` instead
+        #[arg(long)]
+        terminal: bool,
+        #[command(flatten)]
+        wlines: WlinesArgs,
+    },
+    /// Show, change or move the history
+    History {
+        #[command(subcommand)]
+        action: HistoryCommand,
+    },
+    /// Print the most launched programs and when programs are launched
+    Stats {
+        #[arg(default_value_t = 10)]
+        count: usize,
+    },
+    /// Print a completion script for a shell, e.g. for PowerShell's $PROFILE
+    Completions { shell: clap_complete::Shell },
+    /// Start wlines_run at login
+    Autostart {
+        #[command(subcommand)]
+        action: AutostartCommand,
+    },
+    /// Leave a program out of the menu
+    Hide { path: String },
+    /// Show a hidden program in the menu again
+    Unhide { path: String },
+    /// `menu:<name>` shows one of the configured `menus`
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+#[derive(Subcommand)]
+pub enum DirsCommand {
+    /// Record a directory as used
+    Add { path: String },
+}
+
+#[derive(Subcommand)]
+pub enum AutostartCommand {
+    /// Start `wlines_run <ARGS>` at login, the daemon without arguments
+    Install {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Stop starting wlines_run at login
+    Remove,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// Print the history with each program's frecency
+    List,
+    /// Forget a program
+    Remove { path: String },
+    /// Write the history with frecencies to a file
+    Export { file: String },
+    /// Merge an exported history into this one
+    Import { file: String },
+    /// Clear the history
+    Reset,
+}
@@ -0,0 +1,251 @@
+//! Setting, clearing and watching the text on the clipboard
+
+use std::io;
+
+// Formats that ask clipboard managers and the Windows clipboard history to skip the contents
+#[cfg(windows)]
+const EXCLUDE_FROM_MONITORS: &str = "ExcludeClipboardContentFromMonitorProcessing";
+#[cfg(windows)]
+const INCLUDE_IN_HISTORY: &str = "CanIncludeInClipboardHistory";
+
+// Put a copy of the data on the open clipboard in the given format
+#[cfg(windows)]
+unsafe fn set_data(format: u32, data: &[u8]) -> io::Result<()> {
+    use std::ptr;
+    use winapi::um::winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+    use winapi::um::winuser::SetClipboardData;
+
+    let memory = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1));
+    if memory.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), GlobalLock(memory) as *mut u8, data.len());
+    GlobalUnlock(memory);
+
+    // The clipboard owns the memory once it's been set
+    if SetClipboardData(format, memory).is_null() {
+        let error = io::Error::last_os_error();
+        GlobalFree(memory);
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn set_contents(text: &str, secret: bool) -> io::Result<()> {
+    use crate::win::to_wide;
+    use std::ptr;
+    use winapi::um::winuser::{
+        CloseClipboard, EmptyClipboard, OpenClipboard, RegisterClipboardFormatW, CF_UNICODETEXT,
+    };
+
+    let bytes: Vec<u8> = to_wide(text)
+        .iter()
+        .flat_map(|unit| unit.to_ne_bytes())
+        .collect();
+    unsafe {
+        if OpenClipboard(ptr::null_mut()) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        EmptyClipboard();
+        let mut result = set_data(CF_UNICODETEXT, &bytes);
+        if secret {
+            let exclude = RegisterClipboardFormatW(to_wide(EXCLUDE_FROM_MONITORS).as_ptr());
+            let history = RegisterClipboardFormatW(to_wide(INCLUDE_IN_HISTORY).as_ptr());
+            result = result
+                .and_then(|()| set_data(exclude, &[]))
+                .and_then(|()| set_data(history, &0u32.to_ne_bytes()));
+        }
+        CloseClipboard();
+        result
+    }
+}
+
+/// Replace the clipboard contents with text
+#[cfg(windows)]
+pub fn set_text(text: &str) -> io::Result<()> {
+    set_contents(text, false)
+}
+
+/// Replace the clipboard contents with a password or similar, which is kept out of clipboard
+/// histories. Returns the clipboard's sequence number for `clear_if_unchanged`.
+#[cfg(windows)]
+pub fn set_secret_text(text: &str) -> io::Result<u32> {
+    use winapi::um::winuser::GetClipboardSequenceNumber;
+
+    set_contents(text, true)?;
+    Ok(unsafe { GetClipboardSequenceNumber() })
+}
+
+/// Empty the clipboard unless something else has been copied since the sequence number
+#[cfg(windows)]
+pub fn clear_if_unchanged(sequence_number: u32) -> io::Result<()> {
+    use std::ptr;
+    use winapi::um::winuser::{
+        CloseClipboard, EmptyClipboard, GetClipboardSequenceNumber, OpenClipboard,
+    };
+
+    unsafe {
+        if GetClipboardSequenceNumber() != sequence_number {
+            return Ok(());
+        }
+        if OpenClipboard(ptr::null_mut()) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        EmptyClipboard();
+        CloseClipboard();
+    }
+    Ok(())
+}
+
+// Whether whoever set the clipboard asked for it to be left out of clipboard histories
+#[cfg(windows)]
+fn is_excluded() -> bool {
+    use crate::win::to_wide;
+    use winapi::um::winuser::{IsClipboardFormatAvailable, RegisterClipboardFormatW};
+
+    unsafe {
+        let exclude = RegisterClipboardFormatW(to_wide(EXCLUDE_FROM_MONITORS).as_ptr());
+        IsClipboardFormatAvailable(exclude) != 0
+    }
+}
+
+// Text currently on the clipboard, if it holds any
+#[cfg(windows)]
+fn get_text() -> io::Result<Option<String>> {
+    use std::ptr;
+    use std::thread;
+    use std::time::Duration;
+    use winapi::um::winbase::{GlobalLock, GlobalSize, GlobalUnlock};
+    use winapi::um::winuser::{
+        CloseClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, CF_UNICODETEXT,
+    };
+
+    unsafe {
+        // Whoever just changed the clipboard may still have it open
+        let mut attempts = 0;
+        while OpenClipboard(ptr::null_mut()) == 0 {
+            attempts += 1;
+            if attempts == 10 {
+                return Err(io::Error::last_os_error());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        if IsClipboardFormatAvailable(CF_UNICODETEXT) == 0 || is_excluded() {
+            CloseClipboard();
+            return Ok(None);
+        }
+        let memory = GetClipboardData(CF_UNICODETEXT);
+        if memory.is_null() {
+            let error = io::Error::last_os_error();
+            CloseClipboard();
+            return Err(error);
+        }
+        let data = GlobalLock(memory) as *const u16;
+        let len = GlobalSize(memory) / std::mem::size_of::<u16>();
+        let text = crate::win::from_wide(std::slice::from_raw_parts(data, len));
+        GlobalUnlock(memory);
+        CloseClipboard();
+        Ok(Some(text))
+    }
+}
+
+/// Call `on_change` with the clipboard's text whenever it changes, until the process exits.
+/// Secrets such as passwords are skipped.
+#[cfg(windows)]
+pub fn watch(mut on_change: impl FnMut(String)) -> io::Result<()> {
+    use crate::win::to_wide;
+    use std::mem;
+    use std::ptr;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{
+        AddClipboardFormatListener, CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW,
+        PostMessageW, RegisterClassW, HWND_MESSAGE, MSG, WM_APP, WM_CLIPBOARDUPDATE, WNDCLASSW,
+    };
+
+    // The notification is sent to the window procedure, so it's posted back to the loop below
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        message: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if message == WM_CLIPBOARDUPDATE {
+            PostMessageW(hwnd, WM_APP, 0, 0);
+            return 0;
+        }
+        DefWindowProcW(hwnd, message, wparam, lparam)
+    }
+
+    let class_name = to_wide("wlines_run_clipboard");
+    unsafe {
+        let mut class: WNDCLASSW = mem::zeroed();
+        class.lpfnWndProc = Some(window_proc);
+        class.lpszClassName = class_name.as_ptr();
+        if RegisterClassW(&class) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let window = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if window.is_null() || AddClipboardFormatListener(window) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut message: MSG = mem::zeroed();
+        while GetMessageW(&mut message, ptr::null_mut(), 0, 0) > 0 {
+            if message.message == WM_APP {
+                if let Ok(Some(text)) = get_text() {
+                    on_change(text);
+                }
+            } else {
+                DispatchMessageW(&message);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn watch(_on_change: impl FnMut(String)) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The clipboard is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn set_text(_text: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The clipboard is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn set_secret_text(_text: &str) -> io::Result<u32> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The clipboard is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn clear_if_unchanged(_sequence_number: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The clipboard is only supported on Windows",
+    ))
+}
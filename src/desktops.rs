@@ -0,0 +1,211 @@
+//! Listing virtual desktops and switching between them
+
+use std::io;
+
+pub struct Desktop {
+    pub number: usize,
+    pub name: String,
+    pub current: bool,
+    // The GUID as stored in the registry
+    #[cfg_attr(not(windows), allow(dead_code))]
+    id: Vec<u8>,
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod desktop_manager {
+    use winapi::shared::guiddef::{GUID, REFGUID};
+    use winapi::shared::minwindef::BOOL;
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::HRESULT;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::RIDL;
+
+    RIDL! {#[uuid(0xa5cd92ff, 0x29be, 0x454c, 0x8d, 0x04, 0xd8, 0x28, 0x79, 0xfb, 0x3f, 0x1b)]
+    interface IVirtualDesktopManager(IVirtualDesktopManagerVtbl): IUnknown(IUnknownVtbl) {
+        fn IsWindowOnCurrentVirtualDesktop(window: HWND, on_current: *mut BOOL,) -> HRESULT,
+        fn GetWindowDesktopId(window: HWND, desktop: *mut GUID,) -> HRESULT,
+        fn MoveWindowToDesktop(window: HWND, desktop: REFGUID,) -> HRESULT,
+    }}
+
+    pub const CLSID_VIRTUAL_DESKTOP_MANAGER: GUID = GUID {
+        Data1: 0xaa50_9086,
+        Data2: 0x5ca9,
+        Data3: 0x4c25,
+        Data4: [0x8f, 0x95, 0x58, 0x9d, 0x3c, 0x07, 0xb4, 0x8a],
+    };
+}
+
+#[cfg(windows)]
+const DESKTOPS_KEY: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\VirtualDesktops";
+
+// Windows 11 keeps the current desktop next to the list, Windows 10 under the login session
+#[cfg(windows)]
+fn current_desktop_id() -> Option<Vec<u8>> {
+    use crate::registry::{get_binary, HKEY_CURRENT_USER};
+    use winapi::um::processthreadsapi::{GetCurrentProcessId, ProcessIdToSessionId};
+
+    if let Some(id) = get_binary(HKEY_CURRENT_USER, DESKTOPS_KEY, "CurrentVirtualDesktop") {
+        return Some(id);
+    }
+    let mut session = 0;
+    unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session) };
+    let path = format!(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\SessionInfo\\{}\\VirtualDesktops",
+        session
+    );
+    get_binary(HKEY_CURRENT_USER, &path, "CurrentVirtualDesktop")
+}
+
+#[cfg(windows)]
+fn guid_string(id: &[u8]) -> String {
+    format!(
+        "{{{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+        id[3], id[2], id[1], id[0], id[5], id[4], id[7], id[6],
+        id[8], id[9], id[10], id[11], id[12], id[13], id[14], id[15]
+    )
+}
+
+/// The virtual desktops in the order they're shown in Task View
+#[cfg(windows)]
+pub fn list() -> io::Result<Vec<Desktop>> {
+    use crate::registry::{get_binary, get_string, HKEY_CURRENT_USER};
+
+    // Before a second desktop is created the list doesn't exist
+    let ids = match get_binary(HKEY_CURRENT_USER, DESKTOPS_KEY, "VirtualDesktopIDs") {
+        Some(ids) if ids.len() >= 16 => ids,
+        _ => {
+            return Ok(vec![Desktop {
+                number: 1,
+                name: String::from("Desktop 1"),
+                current: true,
+                id: Vec::new(),
+            }])
+        }
+    };
+    let current = current_desktop_id();
+    Ok(ids
+        .chunks_exact(16)
+        .enumerate()
+        .map(|(i, id)| {
+            let path = format!("{}\\Desktops\\{}", DESKTOPS_KEY, guid_string(id));
+            Desktop {
+                number: i + 1,
+                name: get_string(HKEY_CURRENT_USER, &path, "Name")
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| format!("Desktop {}", i + 1)),
+                current: current.as_deref() == Some(id),
+                id: id.to_vec(),
+            }
+        })
+        .collect())
+}
+
+/// Switch to the desktop, which has no public API, by pressing Ctrl+Win+Left or Right
+#[cfg(windows)]
+pub fn switch_to(desktops: &[Desktop], desktop: &Desktop) -> io::Result<()> {
+    use crate::keyboard::press_keys;
+    use std::{thread, time::Duration};
+    use winapi::um::winuser::{VK_LCONTROL, VK_LEFT, VK_LWIN, VK_RIGHT};
+
+    let current = match desktops.iter().find(|desktop| desktop.current) {
+        Some(current) => current.number,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Couldn't find the current desktop",
+            ))
+        }
+    };
+    let key = if desktop.number > current {
+        VK_RIGHT
+    } else {
+        VK_LEFT
+    };
+    for _ in 0..desktop.number.abs_diff(current) {
+        press_keys(&[VK_LCONTROL, VK_LWIN, key])?;
+        // Presses sent during the animation are dropped
+        thread::sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+/// Move the focused window to the desktop. Windows only allows this for some windows, and fails
+/// with access denied for others.
+#[cfg(windows)]
+pub fn move_focused_window(desktop: &Desktop) -> io::Result<()> {
+    use crate::win::init_com;
+    use desktop_manager::{IVirtualDesktopManager, CLSID_VIRTUAL_DESKTOP_MANAGER};
+    use std::ptr;
+    use winapi::shared::guiddef::GUID;
+    use winapi::um::combaseapi::{CoCreateInstance, CLSCTX_ALL};
+    use winapi::um::winuser::GetForegroundWindow;
+    use winapi::Interface;
+
+    if desktop.id.len() != 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "There's only one desktop",
+        ));
+    }
+    let id = &desktop.id;
+    let guid = GUID {
+        Data1: u32::from_le_bytes([id[0], id[1], id[2], id[3]]),
+        Data2: u16::from_le_bytes([id[4], id[5]]),
+        Data3: u16::from_le_bytes([id[6], id[7]]),
+        Data4: [id[8], id[9], id[10], id[11], id[12], id[13], id[14], id[15]],
+    };
+
+    init_com();
+    unsafe {
+        let window = GetForegroundWindow();
+        if window.is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No window is focused",
+            ));
+        }
+        let mut manager: *mut IVirtualDesktopManager = ptr::null_mut();
+        let result = CoCreateInstance(
+            &CLSID_VIRTUAL_DESKTOP_MANAGER,
+            ptr::null_mut(),
+            CLSCTX_ALL,
+            &IVirtualDesktopManager::uuidof(),
+            &mut manager as *mut *mut IVirtualDesktopManager as *mut _,
+        );
+        if result < 0 {
+            return Err(io::Error::from_raw_os_error(result));
+        }
+        let result = (*manager).MoveWindowToDesktop(window, &guid);
+        (*manager).Release();
+        if result < 0 {
+            return Err(io::Error::from_raw_os_error(result));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn list() -> io::Result<Vec<Desktop>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Virtual desktops are only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn switch_to(_desktops: &[Desktop], _desktop: &Desktop) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Virtual desktops are only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn move_focused_window(_desktop: &Desktop) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Virtual desktops are only supported on Windows",
+    ))
+}
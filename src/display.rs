@@ -0,0 +1,133 @@
+//! Switching between projection modes and display resolutions
+
+use serde::Deserialize;
+use std::io;
+
+/// How the displays are used, like in the Win+P menu
+#[derive(Clone, Copy)]
+pub enum Topology {
+    /// PC screen only
+    Internal,
+    Clone,
+    Extend,
+    /// Second screen only
+    External,
+}
+
+/// A resolution and refresh rate from the configuration
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DisplayPreset {
+    pub width: u32,
+    pub height: u32,
+    /// Refresh rate in Hz, or 0 to keep the current one
+    pub refresh_rate: u32,
+    /// Device name such as `\\.\DISPLAY2`, or empty for the primary display
+    pub display: String,
+}
+
+#[cfg(windows)]
+#[link(name = "user32")]
+extern "system" {
+    fn SetDisplayConfig(
+        path_count: u32,
+        paths: *mut std::ffi::c_void,
+        mode_count: u32,
+        modes: *mut std::ffi::c_void,
+        flags: u32,
+    ) -> i32;
+}
+
+#[cfg(windows)]
+pub fn set_topology(topology: Topology) -> io::Result<()> {
+    use std::ptr;
+    use winapi::um::wingdi::{
+        SDC_APPLY, SDC_TOPOLOGY_CLONE, SDC_TOPOLOGY_EXTEND, SDC_TOPOLOGY_EXTERNAL,
+        SDC_TOPOLOGY_INTERNAL,
+    };
+
+    let flag = match topology {
+        Topology::Internal => SDC_TOPOLOGY_INTERNAL,
+        Topology::Clone => SDC_TOPOLOGY_CLONE,
+        Topology::Extend => SDC_TOPOLOGY_EXTEND,
+        Topology::External => SDC_TOPOLOGY_EXTERNAL,
+    };
+    let result =
+        unsafe { SetDisplayConfig(0, ptr::null_mut(), 0, ptr::null_mut(), flag | SDC_APPLY) };
+    if result != 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+/// Change the resolution and refresh rate of a display, keeping the change after logging out
+#[cfg(windows)]
+pub fn apply_preset(preset: &DisplayPreset) -> io::Result<()> {
+    use crate::win::to_wide;
+    use std::{mem, ptr};
+    use winapi::um::wingdi::{DEVMODEW, DM_DISPLAYFREQUENCY, DM_PELSHEIGHT, DM_PELSWIDTH};
+    use winapi::um::winuser::{
+        ChangeDisplaySettingsExW, EnumDisplaySettingsW, CDS_UPDATEREGISTRY, DISP_CHANGE_BADMODE,
+        DISP_CHANGE_RESTART, DISP_CHANGE_SUCCESSFUL, ENUM_CURRENT_SETTINGS,
+    };
+
+    let device = to_wide(&preset.display);
+    let device = if preset.display.is_empty() {
+        ptr::null()
+    } else {
+        device.as_ptr()
+    };
+    unsafe {
+        let mut mode: DEVMODEW = mem::zeroed();
+        mode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+        if EnumDisplaySettingsW(device, ENUM_CURRENT_SETTINGS, &mut mode) == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No display named '{}'", preset.display),
+            ));
+        }
+        mode.dmPelsWidth = preset.width;
+        mode.dmPelsHeight = preset.height;
+        mode.dmFields = DM_PELSWIDTH | DM_PELSHEIGHT;
+        if preset.refresh_rate != 0 {
+            mode.dmDisplayFrequency = preset.refresh_rate;
+            mode.dmFields |= DM_DISPLAYFREQUENCY;
+        }
+        match ChangeDisplaySettingsExW(
+            device,
+            &mut mode,
+            ptr::null_mut(),
+            CDS_UPDATEREGISTRY,
+            ptr::null_mut(),
+        ) {
+            DISP_CHANGE_SUCCESSFUL => Ok(()),
+            DISP_CHANGE_RESTART => {
+                Err(io::Error::other("The change takes effect after restarting"))
+            }
+            DISP_CHANGE_BADMODE => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The display doesn't support the mode",
+            )),
+            result => Err(io::Error::other(format!(
+                "The display settings couldn't be changed ({})",
+                result
+            ))),
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn set_topology(_topology: Topology) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Display settings are only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn apply_preset(_preset: &DisplayPreset) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Display settings are only supported on Windows",
+    ))
+}
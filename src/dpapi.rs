@@ -0,0 +1,75 @@
+//! Encrypting data for the current user with DPAPI, so only they can read it on this machine
+
+use std::io;
+
+#[cfg(windows)]
+fn crypt(data: &[u8], protect: bool) -> io::Result<Vec<u8>> {
+    use std::ptr;
+    use winapi::um::dpapi::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN};
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::wincrypt::DATA_BLOB;
+
+    let mut input = DATA_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    };
+    let mut output = DATA_BLOB {
+        cbData: 0,
+        pbData: ptr::null_mut(),
+    };
+    unsafe {
+        let result = if protect {
+            CryptProtectData(
+                &mut input,
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        } else {
+            CryptUnprotectData(
+                &mut input,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        };
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let bytes = std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec();
+        LocalFree(output.pbData as *mut _);
+        Ok(bytes)
+    }
+}
+
+#[cfg(windows)]
+pub fn protect(data: &[u8]) -> io::Result<Vec<u8>> {
+    crypt(data, true)
+}
+
+#[cfg(windows)]
+pub fn unprotect(data: &[u8]) -> io::Result<Vec<u8>> {
+    crypt(data, false)
+}
+
+#[cfg(not(windows))]
+pub fn protect(_data: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Encryption is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn unprotect(_data: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Encryption is only supported on Windows",
+    ))
+}
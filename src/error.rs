@@ -0,0 +1,84 @@
+//! Errors that end wlines_run, each with its own exit code so that scripts can tell them apart
+
+use std::io;
+use std::process;
+use thiserror::Error;
+
+/// Exit code of errors without a more specific one
+pub const EXIT_FAILURE: i32 = 1;
+// 2 is used by clap for invalid arguments
+pub const EXIT_INDEX_MISSING: i32 = 3;
+pub const EXIT_FRONTEND_FAILED: i32 = 4;
+pub const EXIT_LAUNCH_FAILED: i32 = 5;
+pub const EXIT_CANCELLED: i32 = 6;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("There's no index yet, run `wlines_run index` first")]
+    IndexMissing,
+    #[error("Couldn't read {path}: {source}")]
+    Read { path: String, source: io::Error },
+    #[error("Couldn't write to {path}: {source}")]
+    Write { path: String, source: io::Error },
+    #[error("Couldn't parse {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("{0}")]
+    Config(String),
+    #[error("Couldn't run {program}: {source}")]
+    Frontend { program: String, source: io::Error },
+    #[error("Couldn't start \"{path}\": {source}")]
+    Launch { path: String, source: io::Error },
+    // The error itself has been reported in the menu already
+    #[error("A chosen program couldn't be started")]
+    LaunchFailed,
+    #[error("Couldn't {action}: {source}")]
+    Io {
+        action: &'static str,
+        source: io::Error,
+    },
+    #[error("%AppData% isn't set, start wlines_run with --portable to keep its data next to it")]
+    NoDataDir,
+    #[error("No program matches '{0}'")]
+    NoMatch(String),
+    #[error("\"{0}\" isn't in the history")]
+    NotInHistory(String),
+    #[error("Menu names may only contain letters, digits, '-' and '_'")]
+    InvalidMenuName,
+    #[error("Invalid duration '{0}', expected e.g. 25m, 1h30m or 90s")]
+    InvalidDuration(String),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::IndexMissing => EXIT_INDEX_MISSING,
+            Error::Frontend { .. } => EXIT_FRONTEND_FAILED,
+            Error::Launch { .. } | Error::LaunchFailed => EXIT_LAUNCH_FAILED,
+            Error::Cancelled => EXIT_CANCELLED,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Log the error and exit with its code
+pub fn exit(err: Error) -> ! {
+    if let Error::Cancelled = err {
+        log::info!("{}", err);
+    } else {
+        log::error!("{}", err);
+    }
+    process::exit(err.exit_code())
+}
+
+pub trait OrExit<T> {
+    /// The value, or exit with the error's code
+    fn or_exit(self) -> T;
+}
+
+impl<T> OrExit<T> for Result<T, Error> {
+    fn or_exit(self) -> T {
+        self.unwrap_or_else(|err| exit(err))
+    }
+}
@@ -0,0 +1,112 @@
+//! Programs that can show the menus. Like dmenu, they read the lines from stdin and print the
+//! chosen one.
+
+pub trait Frontend {
+    /// The program that is started when no `wlines_path` is configured
+    fn program(&self) -> &'static str;
+
+    /// Arguments passed before the configured and command line ones
+    fn args(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Arguments that show a label in front of the input
+    fn prompt_args(&self, label: &str) -> Vec<String> {
+        vec![String::from("-p"), label.to_string()]
+    }
+
+    /// The chosen lines, or what was typed if nothing matched, from the exit code and output of
+    /// the program. `None` if the menu was cancelled.
+    fn selection(&self, code: Option<i32>, output: &str) -> Option<String> {
+        if code == Some(0) {
+            Some(output.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Wlines;
+
+impl Frontend for Wlines {
+    fn program(&self) -> &'static str {
+        "wlines"
+    }
+}
+
+/// fzf in the terminal wlines_run was started from
+pub struct Fzf;
+
+impl Frontend for Fzf {
+    fn program(&self) -> &'static str {
+        "fzf"
+    }
+
+    fn args(&self) -> &'static [&'static str] {
+        &["--print-query", "--multi"]
+    }
+
+    fn prompt_args(&self, label: &str) -> Vec<String> {
+        vec![format!("--prompt={}> ", label)]
+    }
+
+    // The query comes first, then the chosen lines. Without a match fzf exits with 1, and with 130
+    // when it's cancelled.
+    fn selection(&self, code: Option<i32>, output: &str) -> Option<String> {
+        let (query, chosen) = output.split_once('\n').unwrap_or((output, ""));
+        match code {
+            Some(0) => Some(chosen.to_string()),
+            Some(1) if !query.trim().is_empty() => Some(query.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// dmenu, rofi or another program that works like dmenu
+pub struct Dmenu {
+    program: &'static str,
+    args: &'static [&'static str],
+}
+
+impl Frontend for Dmenu {
+    fn program(&self) -> &'static str {
+        self.program
+    }
+
+    fn args(&self) -> &'static [&'static str] {
+        self.args
+    }
+}
+
+pub fn by_name(name: &str) -> Option<Box<dyn Frontend>> {
+    match name {
+        "wlines" => Some(Box::new(Wlines)),
+        "fzf" => Some(Box::new(Fzf)),
+        "rofi" => Some(Box::new(Dmenu {
+            program: "rofi",
+            args: &["-dmenu"],
+        })),
+        "dmenu" => Some(Box::new(Dmenu {
+            program: "dmenu",
+            args: &[],
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fzf_selection() {
+        assert_eq!(
+            Fzf.selection(Some(0), "fire\nS] Firefox.lnk: \n")
+                .as_deref(),
+            Some("S] Firefox.lnk: \n")
+        );
+        assert_eq!(Fzf.selection(Some(1), "=2+2\n").as_deref(), Some("=2+2"));
+        assert_eq!(Fzf.selection(Some(1), "\n"), None);
+        assert_eq!(Fzf.selection(Some(130), "fire\n"), None);
+    }
+}
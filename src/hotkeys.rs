@@ -0,0 +1,131 @@
+//! Global hotkeys like `Win+Alt+Space`, which the daemon registers so that no other hotkey tool is
+//! needed
+
+#[cfg(windows)]
+use std::io;
+
+const MOD_ALT: u32 = 0x1;
+const MOD_CONTROL: u32 = 0x2;
+const MOD_SHIFT: u32 = 0x4;
+const MOD_WIN: u32 = 0x8;
+
+/// Modifiers and virtual-key code, as `RegisterHotKey` takes them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub key: u32,
+}
+
+fn virtual_key(name: &str) -> Option<u32> {
+    let name = name.to_ascii_lowercase();
+    let key = match name.as_str() {
+        "space" => 0x20,
+        "tab" => 0x09,
+        "enter" | "return" => 0x0d,
+        "escape" | "esc" => 0x1b,
+        "backspace" => 0x08,
+        "insert" => 0x2d,
+        "delete" | "del" => 0x2e,
+        "home" => 0x24,
+        "end" => 0x23,
+        "pageup" => 0x21,
+        "pagedown" => 0x22,
+        "left" => 0x25,
+        "up" => 0x26,
+        "right" => 0x27,
+        "down" => 0x28,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                // Letters and digits are their uppercase ASCII code
+                (Some(c), None) if c.is_ascii_alphanumeric() => c.to_ascii_uppercase() as u32,
+                (Some('f'), Some(_)) => match name[1..].parse::<u32>() {
+                    Ok(n @ 1..=24) => 0x70 + n - 1,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+/// Parse a hotkey like `Ctrl+Alt+R` or `Win+F1`, in any case
+pub fn parse(text: &str) -> Option<Hotkey> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = virtual_key(parts.pop()?)?;
+    let mut modifiers = 0;
+    for part in parts {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "alt" => MOD_ALT,
+            "ctrl" | "control" => MOD_CONTROL,
+            "shift" => MOD_SHIFT,
+            "win" => MOD_WIN,
+            _ => return None,
+        };
+    }
+    Some(Hotkey { modifiers, key })
+}
+
+/// Register the hotkeys for the calling thread, whose message loop then gets a `WM_HOTKEY` with
+/// the index of each one that is pressed. Hotkeys that are taken by Windows or another program are
+/// skipped.
+#[cfg(windows)]
+pub fn register(hotkeys: &[(String, Hotkey)]) {
+    use std::ptr;
+    use winapi::um::winuser::{RegisterHotKey, MOD_NOREPEAT};
+
+    for (i, (name, hotkey)) in hotkeys.iter().enumerate() {
+        let registered = unsafe {
+            RegisterHotKey(
+                ptr::null_mut(),
+                i as i32,
+                hotkey.modifiers | MOD_NOREPEAT as u32,
+                hotkey.key,
+            )
+        };
+        if registered == 0 {
+            log::warn!("Couldn't register {}: {}", name, io::Error::last_os_error());
+        }
+    }
+}
+
+/// Unregister the hotkeys that the calling thread registered
+#[cfg(windows)]
+pub fn unregister(hotkeys: &[(String, Hotkey)]) {
+    use std::ptr;
+    use winapi::um::winuser::UnregisterHotKey;
+
+    for i in 0..hotkeys.len() {
+        unsafe {
+            UnregisterHotKey(ptr::null_mut(), i as i32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hotkeys() {
+        assert_eq!(
+            parse("Win+Alt+Space"),
+            Some(Hotkey {
+                modifiers: MOD_WIN | MOD_ALT,
+                key: 0x20
+            })
+        );
+        assert_eq!(
+            parse("ctrl+shift+r"),
+            Some(Hotkey {
+                modifiers: MOD_CONTROL | MOD_SHIFT,
+                key: b'R' as u32
+            })
+        );
+        assert_eq!(parse("Win+F12").map(|hotkey| hotkey.key), Some(0x7b));
+        assert_eq!(parse("Win+F25"), None);
+        assert_eq!(parse("Hyper+A"), None);
+        assert_eq!(parse("Win+"), None);
+    }
+}
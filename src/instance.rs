@@ -0,0 +1,93 @@
+//! Keeping a second `run` from opening another menu while one is open, which happens when a
+//! hotkey is pressed twice
+
+use std::fs;
+use std::path::PathBuf;
+
+// The frontend of the open menu, for the next instance to bring it to the front
+fn pid_path() -> PathBuf {
+    std::env::temp_dir().join("wlines_run_menu.pid")
+}
+
+/// Held while a menu is open
+pub struct MenuGuard {
+    #[cfg(windows)]
+    mutex: winapi::um::winnt::HANDLE,
+}
+
+impl MenuGuard {
+    /// Remember the process of the menu's frontend
+    pub fn set_frontend(&self, pid: u32) {
+        let _ = fs::write(pid_path(), pid.to_string());
+    }
+}
+
+impl Drop for MenuGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(pid_path());
+        #[cfg(windows)]
+        unsafe {
+            winapi::um::handleapi::CloseHandle(self.mutex);
+        }
+    }
+}
+
+/// The guard, or `None` if another instance has a menu open, which is then brought to the front
+#[cfg(windows)]
+pub fn acquire() -> Option<MenuGuard> {
+    use crate::win::to_wide;
+    use std::io;
+    use std::ptr;
+    use winapi::shared::winerror::ERROR_ALREADY_EXISTS;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::synchapi::CreateMutexW;
+
+    let name = to_wide("Local\\wlines_run_menu");
+    unsafe {
+        let mutex = CreateMutexW(ptr::null_mut(), 0, name.as_ptr());
+        if mutex.is_null() {
+            // Without the mutex, opening a second menu is better than opening none
+            return Some(MenuGuard { mutex });
+        }
+        if io::Error::last_os_error().raw_os_error() == Some(ERROR_ALREADY_EXISTS as i32) {
+            CloseHandle(mutex);
+            if let Some(pid) = fs::read_to_string(pid_path())
+                .ok()
+                .and_then(|pid| pid.trim().parse().ok())
+            {
+                focus_process(pid);
+            }
+            return None;
+        }
+        Some(MenuGuard { mutex })
+    }
+}
+
+#[cfg(not(windows))]
+pub fn acquire() -> Option<MenuGuard> {
+    Some(MenuGuard {})
+}
+
+// Bring the first visible window of a process to the front
+#[cfg(windows)]
+fn focus_process(pid: u32) {
+    use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{
+        EnumWindows, GetWindowThreadProcessId, IsWindowVisible, SetForegroundWindow,
+    };
+
+    unsafe extern "system" fn enum_window(window: HWND, lparam: LPARAM) -> BOOL {
+        let mut window_pid: DWORD = 0;
+        GetWindowThreadProcessId(window, &mut window_pid);
+        if window_pid == lparam as DWORD && IsWindowVisible(window) != 0 {
+            SetForegroundWindow(window);
+            return 0;
+        }
+        TRUE
+    }
+
+    unsafe {
+        EnumWindows(Some(enum_window), pid as LPARAM);
+    }
+}
@@ -0,0 +1,88 @@
+//! Typing text and pressing key combinations with `SendInput`
+
+use std::io;
+
+/// Type text into the focused window as if it was typed on the keyboard
+#[cfg(windows)]
+pub fn type_text(text: &str) -> io::Result<()> {
+    use std::mem;
+    use winapi::um::winuser::{
+        SendInput, INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
+    };
+
+    let mut inputs = Vec::new();
+    for unit in text.encode_utf16() {
+        // Newlines are only understood as the Enter key
+        let (key, scan, flags) = if unit == u16::from(b'\n') {
+            (0x0d, 0, 0)
+        } else if unit == u16::from(b'\r') {
+            continue;
+        } else {
+            (0, unit, KEYEVENTF_UNICODE)
+        };
+        for up in [0, KEYEVENTF_KEYUP] {
+            unsafe {
+                let mut input: INPUT = mem::zeroed();
+                input.type_ = INPUT_KEYBOARD;
+                let keyboard = input.u.ki_mut();
+                keyboard.wVk = key;
+                keyboard.wScan = scan;
+                keyboard.dwFlags = flags | up;
+                inputs.push(input);
+            }
+        }
+    }
+
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_mut_ptr(),
+            mem::size_of::<INPUT>() as i32,
+        )
+    };
+    if sent as usize != inputs.len() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn type_text(_text: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Typing is only supported on Windows",
+    ))
+}
+
+/// Press a key combination, e.g. `[VK_LCONTROL, VK_LWIN, VK_RIGHT]`, releasing the keys in reverse
+#[cfg(windows)]
+pub fn press_keys(keys: &[i32]) -> io::Result<()> {
+    use std::mem;
+    use winapi::um::winuser::{SendInput, INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP};
+
+    let presses = keys.iter().map(|&key| (key, 0));
+    let releases = keys.iter().rev().map(|&key| (key, KEYEVENTF_KEYUP));
+    let mut inputs: Vec<INPUT> = presses
+        .chain(releases)
+        .map(|(key, flags)| unsafe {
+            let mut input: INPUT = mem::zeroed();
+            input.type_ = INPUT_KEYBOARD;
+            let keyboard = input.u.ki_mut();
+            keyboard.wVk = key as u16;
+            keyboard.dwFlags = flags;
+            input
+        })
+        .collect();
+
+    let sent = unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_mut_ptr(),
+            mem::size_of::<INPUT>() as i32,
+        )
+    };
+    if sent as usize != inputs.len() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,583 @@
+//! Starting programs, documents and command lines, directly or through the shell
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+
+/// Initial state of the launched program's window
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowState {
+    #[default]
+    Normal,
+    Minimized,
+    Maximized,
+}
+
+impl WindowState {
+    fn name(self) -> &'static str {
+        match self {
+            WindowState::Normal => "normal",
+            WindowState::Minimized => "minimized",
+            WindowState::Maximized => "maximized",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LaunchOptions<'a> {
+    /// Shell verb such as `runas`, or `None` for the default action
+    pub verb: Option<&'a str>,
+    /// Directory the program starts in, or `None` to inherit ours
+    pub working_dir: Option<&'a str>,
+    /// Environment variables set or overridden for the program
+    pub environment: Option<&'a HashMap<String, String>>,
+    pub window_state: WindowState,
+    /// Print what would be executed instead of executing it
+    pub dry_run: bool,
+}
+
+fn print_dry_run(file: &str, parameters: &str, options: &LaunchOptions) {
+    println!("Dry run, not launching:");
+    println!("  File:        {}", file);
+    println!("  Parameters:  {}", parameters);
+    println!(
+        "  Directory:   {}",
+        options.working_dir.unwrap_or("(inherited)")
+    );
+    println!("  Verb:        {}", options.verb.unwrap_or("(default)"));
+    println!("  Window:      {}", options.window_state.name());
+    if let Some(environment) = options.environment {
+        for (key, value) in environment {
+            println!("  Environment: {}={}", key, value);
+        }
+    }
+    println!();
+}
+
+/// Split typed arguments the way the C runtime does, so that `C:\My Files\a.txt` keeps its
+/// backslashes and `"C:\My Files\a.txt"` stays a single argument
+pub fn split_args(arg_string: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+    let mut chars = arg_string.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut backslashes = 1;
+                while chars.peek() == Some(&'\\') {
+                    chars.next();
+                    backslashes += 1;
+                }
+                if chars.peek() == Some(&'"') {
+                    // Backslashes only escape when followed by a quote
+                    current.extend(std::iter::repeat_n('\\', backslashes / 2));
+                    if backslashes % 2 == 1 {
+                        chars.next();
+                        current.push('"');
+                    }
+                } else {
+                    current.extend(std::iter::repeat_n('\\', backslashes));
+                }
+                in_arg = true;
+            }
+            '"' => {
+                if in_quotes && chars.peek() == Some(&'"') {
+                    // `""` inside quotes is a literal quote
+                    chars.next();
+                    current.push('"');
+                } else {
+                    in_quotes = !in_quotes;
+                }
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+// Quote an argument so that CommandLineToArgvW and the C runtime parse it back unchanged.
+// Arguments with cmd metacharacters are quoted too, since some launches go through cmd.
+fn quote_arg(arg: &str) -> String {
+    const SPECIAL: &[char] = &[
+        ' ', '\t', '\n', '\x0b', '"', '&', '|', '<', '>', '^', '(', ')',
+    ];
+    if !arg.is_empty() && !arg.contains(SPECIAL) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escaped, and so is the quote itself
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Backslashes before the closing quote would otherwise escape it
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+// Join arguments into a single parameter string for the launched program
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Split a command line into the program and its parameters. Like CreateProcess, unquoted
+// programs containing spaces are found by trying each space as the end of the program path.
+fn split_command_line(command_line: &str) -> (&str, &str) {
+    let command_line = command_line.trim();
+    if let Some(rest) = command_line.strip_prefix('"') {
+        return match rest.find('"') {
+            Some(end) => (&rest[..end], rest[end + 1..].trim_start()),
+            None => (rest, ""),
+        };
+    }
+
+    let split_at = |i: usize| (&command_line[..i], command_line[i..].trim_start());
+    let spaces: Vec<usize> = command_line.match_indices(' ').map(|(i, _)| i).collect();
+    for &i in &spaces {
+        let program = std::path::Path::new(&command_line[..i]);
+        if program.is_file() || program.with_extension("exe").is_file() {
+            return split_at(i);
+        }
+    }
+    match spaces.first() {
+        Some(&i) => split_at(i),
+        None => (command_line, ""),
+    }
+}
+
+#[cfg(windows)]
+fn show_command(window_state: WindowState) -> i32 {
+    use winapi::um::winuser::{SW_SHOWMAXIMIZED, SW_SHOWMINNOACTIVE, SW_SHOWNORMAL};
+
+    match window_state {
+        WindowState::Normal => SW_SHOWNORMAL,
+        WindowState::Minimized => SW_SHOWMINNOACTIVE,
+        WindowState::Maximized => SW_SHOWMAXIMIZED,
+    }
+}
+
+// Executables can be started directly, anything else like URLs, folders, documents or programs
+// that are only found on PATH needs the shell to find its handler
+#[cfg(windows)]
+fn is_executable(path: &str) -> bool {
+    let path = std::path::Path::new(path);
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exe"))
+        && path.is_file()
+}
+
+// Our own environment with the overrides applied, as the block CreateProcess expects. Names are
+// case-insensitive on Windows, so an override replaces the variable whatever its case.
+#[cfg(windows)]
+fn environment_block(overrides: &HashMap<String, String>) -> Vec<u16> {
+    use std::ffi::{OsStr, OsString};
+    use std::os::windows::ffi::OsStrExt;
+
+    let mut variables: Vec<(OsString, OsString)> = std::env::vars_os()
+        .filter(|(key, _)| {
+            !overrides
+                .keys()
+                .any(|name| OsStr::new(name).eq_ignore_ascii_case(key))
+        })
+        .collect();
+    variables.extend(
+        overrides
+            .iter()
+            .map(|(key, value)| (key.into(), value.into())),
+    );
+    variables.sort_by_key(|(key, _)| key.to_ascii_uppercase());
+
+    let mut block = Vec::new();
+    for (key, value) in &variables {
+        block.extend(key.encode_wide());
+        block.push('=' as u16);
+        block.extend(value.encode_wide());
+        block.push(0);
+    }
+    // The block ends with an empty string, which also makes an empty block well-formed
+    if variables.is_empty() {
+        block.push(0);
+    }
+    block.push(0);
+    block
+}
+
+// The shell only passes our own environment on, so programs with their own environment are
+// handed to the shell by `start` in a hidden cmd that has it
+#[cfg(windows)]
+fn start_with_environment(
+    path: &str,
+    parameters: &str,
+    environment: &HashMap<String, String>,
+    options: &LaunchOptions,
+) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    let window = match options.window_state {
+        WindowState::Normal => "",
+        WindowState::Minimized => "/min ",
+        WindowState::Maximized => "/max ",
+    };
+    let mut command = std::process::Command::new("cmd");
+    command
+        .raw_arg(format!(
+            "/d /c start \"\" {}\"{}\" {}",
+            window, path, parameters
+        ))
+        .envs(environment)
+        .creation_flags(CREATE_NO_WINDOW);
+    if let Some(dir) = options.working_dir {
+        command.current_dir(dir);
+    }
+    command.spawn()?;
+    Ok(())
+}
+
+// Start an executable in its own process group and console, and outside of any job object we
+// are in when that's allowed, so that closing our console or host doesn't take it down with us
+#[cfg(windows)]
+fn create_process(path: &str, parameters: &str, options: &LaunchOptions) -> io::Result<()> {
+    use crate::win::to_wide;
+    use std::mem;
+    use std::ptr;
+    use winapi::shared::winerror::ERROR_ACCESS_DENIED;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{CreateProcessW, PROCESS_INFORMATION, STARTUPINFOW};
+    use winapi::um::winbase::{
+        CREATE_BREAKAWAY_FROM_JOB, CREATE_NEW_CONSOLE, CREATE_NEW_PROCESS_GROUP,
+        CREATE_UNICODE_ENVIRONMENT, STARTF_USESHOWWINDOW,
+    };
+
+    let mut command_line = to_wide(&format!("\"{}\" {}", path, parameters));
+    let directory = options.working_dir.map(to_wide);
+    let mut environment = options.environment.map(environment_block);
+    let mut flags = CREATE_NEW_CONSOLE
+        | CREATE_NEW_PROCESS_GROUP
+        | CREATE_BREAKAWAY_FROM_JOB
+        | CREATE_UNICODE_ENVIRONMENT;
+    unsafe {
+        let mut startup_info: STARTUPINFOW = mem::zeroed();
+        startup_info.cb = mem::size_of::<STARTUPINFOW>() as u32;
+        startup_info.dwFlags = STARTF_USESHOWWINDOW;
+        startup_info.wShowWindow = show_command(options.window_state) as u16;
+        loop {
+            let mut process_info: PROCESS_INFORMATION = mem::zeroed();
+            let created = CreateProcessW(
+                ptr::null(),
+                command_line.as_mut_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                flags,
+                environment
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |block| block.as_mut_ptr().cast()),
+                directory.as_ref().map_or(ptr::null(), |dir| dir.as_ptr()),
+                &mut startup_info,
+                &mut process_info,
+            );
+            if created != 0 {
+                CloseHandle(process_info.hProcess);
+                CloseHandle(process_info.hThread);
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            // Jobs that don't allow breaking away refuse the whole call
+            if flags & CREATE_BREAKAWAY_FROM_JOB != 0
+                && err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32)
+            {
+                flags &= !CREATE_BREAKAWAY_FROM_JOB;
+                continue;
+            }
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn shell_execute(path: &str, parameters: &str, options: &LaunchOptions) -> io::Result<()> {
+    use crate::win::{init_com, to_wide};
+    use std::mem;
+    use std::ptr;
+    use winapi::um::shellapi::{
+        ShellExecuteExW, SEE_MASK_FLAG_NO_UI, SEE_MASK_NOASYNC, SHELLEXECUTEINFOW,
+    };
+
+    if options.dry_run {
+        print_dry_run(path, parameters, options);
+        return Ok(());
+    }
+
+    if options.verb.is_none() && is_executable(path) {
+        // The shell can still start it when CreateProcess can't, e.g. with the UAC prompt
+        match create_process(path, parameters, options) {
+            Ok(()) => return Ok(()),
+            Err(err) => log::debug!("Couldn't create a process for {}: {}", path, err),
+        }
+    }
+
+    if let Some(environment) = options.environment {
+        match options.verb {
+            None => return start_with_environment(path, parameters, environment, options),
+            // Elevated programs get a fresh environment from Windows anyway
+            Some(verb) => log::warn!(
+                "The environment of \"{}\" can't be set when starting it with '{}'",
+                path,
+                verb
+            ),
+        }
+    }
+
+    let file = to_wide(path);
+    let parameters = to_wide(parameters);
+    let verb = options.verb.map(to_wide);
+    let directory = options.working_dir.map(to_wide);
+
+    // Some shell extensions behind ShellExecute require COM
+    init_com();
+    unsafe {
+        let mut info: SHELLEXECUTEINFOW = mem::zeroed();
+        info.cbSize = mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+        info.fMask = SEE_MASK_NOASYNC | SEE_MASK_FLAG_NO_UI;
+        info.lpVerb = verb.as_ref().map_or(ptr::null(), |verb| verb.as_ptr());
+        info.lpFile = file.as_ptr();
+        info.lpParameters = parameters.as_ptr();
+        info.lpDirectory = directory.as_ref().map_or(ptr::null(), |dir| dir.as_ptr());
+        info.nShow = show_command(options.window_state);
+        if ShellExecuteExW(&mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Launch a program, shortcut or document through the shell, like double-clicking it would
+#[cfg(windows)]
+pub fn launch(path: &str, args: &[String], options: &LaunchOptions) -> io::Result<()> {
+    shell_execute(path, &join_args(args), options)
+}
+
+/// Run a full command line such as `"C:\Program Files\App\uninstall.exe" /S`
+#[cfg(windows)]
+pub fn run_command_line(command_line: &str, options: &LaunchOptions) -> io::Result<()> {
+    let (program, parameters) = split_command_line(command_line);
+    shell_execute(program, parameters, options)
+}
+
+// Whether the terminal command ends up in cmd, like `cmd /s /k` or `wt.exe cmd /s /k`
+#[cfg(windows)]
+fn terminal_runs_cmd(terminal: &str) -> bool {
+    split_args(terminal).iter().any(|arg| {
+        std::path::Path::new(arg)
+            .file_stem()
+            .is_some_and(|stem| stem.eq_ignore_ascii_case("cmd"))
+    })
+}
+
+/// Launch a program inside a terminal command such as `cmd /s /k`, which keeps its output visible
+#[cfg(windows)]
+pub fn launch_in_terminal(
+    terminal: &str,
+    path: &str,
+    args: &[String],
+    options: &LaunchOptions,
+) -> io::Result<()> {
+    let mut command = quote_arg(path);
+    if !args.is_empty() {
+        command = format!("{} {}", command, join_args(args));
+    }
+    // cmd strips the outer quotes off the command after /c or /k, other terminals take the
+    // program and its arguments as they are
+    let command_line = if terminal_runs_cmd(terminal) {
+        format!("{} \"{}\"", terminal, command)
+    } else {
+        format!("{} {}", terminal, command)
+    };
+    run_command_line(&command_line, options)
+}
+
+/// Open an Explorer window for the folder containing the path, with the file selected
+#[cfg(windows)]
+pub fn open_folder(path: &str) -> io::Result<()> {
+    shell_execute(
+        "explorer.exe",
+        &format!("/select,\"{}\"", path),
+        &LaunchOptions::default(),
+    )
+}
+
+/// Launch through `runas /user:<user>` in a new console window where the password is typed
+#[cfg(windows)]
+pub fn launch_as_user(
+    path: &str,
+    args: &[String],
+    user: &str,
+    options: &LaunchOptions,
+) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NEW_CONSOLE;
+
+    // runas can't open shortcuts or documents by itself, so let cmd's start do it
+    let command_line = format!("cmd /c start \"\" \"{}\" {}", path, join_args(args));
+    if options.dry_run {
+        print_dry_run(
+            "runas",
+            &format!("/user:{} {}", user, command_line),
+            options,
+        );
+        return Ok(());
+    }
+    std::process::Command::new("runas")
+        .arg(format!("/user:{}", user))
+        .arg(command_line)
+        .creation_flags(CREATE_NEW_CONSOLE)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn launch_as_user(
+    _path: &str,
+    _args: &[String],
+    _user: &str,
+    _options: &LaunchOptions,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Running as another user is only supported on Windows",
+    ))
+}
+
+/// Run a shell command without a console window, with extra environment variables
+#[cfg(windows)]
+pub fn run_hook(command: &str, environment: &[(&str, &str)]) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    std::process::Command::new("cmd")
+        .arg("/c")
+        .raw_arg(command)
+        .envs(environment.iter().copied())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+/// Start a program in the background without a console window
+#[cfg(windows)]
+pub fn spawn_background(path: &str, args: &[String]) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    std::process::Command::new(path)
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn spawn_background(path: &str, args: &[String]) -> io::Result<()> {
+    std::process::Command::new(path).args(args).spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn run_hook(command: &str, environment: &[(&str, &str)]) -> io::Result<()> {
+    std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(environment.iter().copied())
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn launch_in_terminal(
+    _terminal: &str,
+    path: &str,
+    args: &[String],
+    options: &LaunchOptions,
+) -> io::Result<()> {
+    launch(path, args, options)
+}
+
+#[cfg(not(windows))]
+pub fn run_command_line(command_line: &str, options: &LaunchOptions) -> io::Result<()> {
+    let (program, parameters) = split_command_line(command_line);
+    if options.dry_run {
+        print_dry_run(program, parameters, options);
+        return Ok(());
+    }
+    std::process::Command::new(program)
+        .args(parameters.split_whitespace())
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn open_folder(path: &str) -> io::Result<()> {
+    let folder = std::path::Path::new(path)
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Path has no parent folder"))?;
+    std::process::Command::new("xdg-open").arg(folder).spawn()?;
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn launch(path: &str, args: &[String], options: &LaunchOptions) -> io::Result<()> {
+    if options.dry_run {
+        print_dry_run(path, &join_args(args), options);
+        return Ok(());
+    }
+    if let Some(verb) = options.verb {
+        log::warn!(
+            "Shell verbs are only supported on Windows, ignoring '{}'",
+            verb
+        );
+    }
+    let mut command = std::process::Command::new(path);
+    command.args(args);
+    if let Some(dir) = options.working_dir {
+        command.current_dir(dir);
+    }
+    if let Some(environment) = options.environment {
+        command.envs(environment);
+    }
+    command.spawn()?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Logging to a file in AppData, since there's usually no console to see messages in when
+//! wlines_run is started from a hotkey
+
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+// The log is moved to `<path>.1` when it grows beyond this, replacing the previous one
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+struct Logger {
+    file: Option<Mutex<File>>,
+    verbose: bool,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info || self.verbose
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        eprintln!("{}", record.args());
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Log to stderr and the file at `path`, including debug messages if `verbose`
+pub fn init(path: &str, verbose: bool) {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        let _ = fs::rename(path, format!("{}.1", path));
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .ok()
+        .map(Mutex::new);
+    let logger = Logger { file, verbose };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(if verbose {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        });
+    }
+}
@@ -1,27 +1,217 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod audio;
+mod autostart;
+mod calc;
+mod cli;
+mod clipboard;
+mod desktops;
+mod display;
+mod dpapi;
+mod error;
+mod frontend;
+mod hotkeys;
+mod instance;
+mod keyboard;
+mod launch;
+mod logging;
+mod notify;
+mod passwords;
+mod pe;
+mod pipe;
+mod power;
+#[cfg(windows)]
+mod registry;
+mod services;
+mod shortcut;
+mod tasks;
+mod tray;
+mod uninstall;
+mod units;
+mod vpn;
+mod wifi;
+#[cfg(windows)]
+mod win;
+mod winget;
+
+use chrono::{Datelike, Local, Timelike};
+use display::{DisplayPreset, Topology};
+use error::{Error, OrExit};
+use launch::{LaunchOptions, WindowState};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
-use std::io::Write;
-use std::path::Path;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::process::{Command, Stdio};
 use std::time::SystemTime;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
 struct HistoryEntry {
     rank: u32,
     access: u64,
+    /// Launches per local hour of the day
+    #[serde(default)]
+    hours: [u32; 24],
+    /// Launches per local weekday, starting with Monday
+    #[serde(default)]
+    weekdays: [u32; 7],
+    /// Argument strings the program was launched with, most recent first
+    #[serde(default)]
+    args: Vec<String>,
+    /// When the program was first launched, 0 if it was before this was recorded
+    #[serde(default)]
+    first: u64,
+    /// Times in a row that the program couldn't be started, e.g. a shortcut whose target is gone
+    #[serde(default, skip_serializing_if = "is_zero")]
+    failures: u32,
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+// The earlier of two times, where 0 is unknown
+fn earliest_time(a: u64, b: u64) -> u64 {
+    match (a, b) {
+        (0, time) | (time, 0) => time,
+        (a, b) => a.min(b),
+    }
+}
+
+const MAX_ARGS_HISTORY: usize = 20;
+const RANK_AGING_FACTOR: f64 = 0.9;
+// Frecency is multiplied by this for each failure in a row
+const FAILURE_PENALTY: f64 = 0.5;
+
+impl HistoryEntry {
+    fn record_launch(&mut self, current_time: u64) {
+        let now = Local::now();
+        self.rank += 1;
+        self.access = current_time;
+        if self.first == 0 {
+            self.first = current_time;
+        }
+        self.failures = 0;
+        self.hours[now.hour() as usize] += 1;
+        self.weekdays[now.weekday().num_days_from_monday() as usize] += 1;
+    }
+
+    fn remember_args(&mut self, arg_string: &str) {
+        if arg_string.is_empty() {
+            return;
+        }
+        self.args.retain(|args| args != arg_string);
+        self.args.insert(0, arg_string.to_string());
+        self.args.truncate(MAX_ARGS_HISTORY);
+    }
+
+    // Combine with the entry of the same program from another history, as if the launches of
+    // both had happened here
+    fn merge(&mut self, other: &HistoryEntry) {
+        self.rank += other.rank;
+        self.access = self.access.max(other.access);
+        self.failures = self.failures.max(other.failures);
+        self.first = earliest_time(self.first, other.first);
+        for (count, other) in self.hours.iter_mut().zip(other.hours.iter()) {
+            *count += other;
+        }
+        for (count, other) in self.weekdays.iter_mut().zip(other.weekdays.iter()) {
+            *count += other;
+        }
+        for args in &other.args {
+            if !self.args.contains(args) {
+                self.args.push(args.clone());
+            }
+        }
+        self.args.truncate(MAX_ARGS_HISTORY);
+    }
+
+    // Add the launches that happened between `original` and `changed`, for when another instance
+    // saved this entry in the meantime
+    fn apply_changes(&mut self, original: &HistoryEntry, changed: &HistoryEntry) {
+        self.rank += changed.rank.saturating_sub(original.rank);
+        self.access = self.access.max(changed.access);
+        if changed.failures != original.failures {
+            self.failures = changed.failures;
+        }
+        self.first = earliest_time(self.first, changed.first);
+        let hours = changed.hours.iter().zip(original.hours.iter());
+        for (count, (changed, original)) in self.hours.iter_mut().zip(hours) {
+            *count += changed.saturating_sub(*original);
+        }
+        let weekdays = changed.weekdays.iter().zip(original.weekdays.iter());
+        for (count, (changed, original)) in self.weekdays.iter_mut().zip(weekdays) {
+            *count += changed.saturating_sub(*original);
+        }
+        // The arguments used since are the ones moved in front of the previously most recent ones
+        let used = match original.args.first() {
+            Some(first) => changed
+                .args
+                .iter()
+                .take_while(|args| *args != first)
+                .collect(),
+            None => changed.args.iter().collect::<Vec<_>>(),
+        };
+        for args in used.into_iter().rev() {
+            self.remember_args(args);
+        }
+    }
+}
+
+// A history entry as exported, with its frecency at the time for reference
+#[derive(Serialize)]
+struct ExportedEntry<'a> {
+    #[serde(flatten)]
+    entry: &'a HistoryEntry,
+    frecency: f64,
+}
+
+// Current local time, for boosting programs usually launched at this hour and weekday
+struct LaunchContext {
+    hour: usize,
+    weekday: usize,
+    weight: f64,
+}
+
+impl LaunchContext {
+    fn now(weight: f64) -> Self {
+        let now = Local::now();
+        LaunchContext {
+            hour: now.hour() as usize,
+            weekday: now.weekday().num_days_from_monday() as usize,
+            weight,
+        }
+    }
+
+    fn boost(&self, history: &HistoryEntry) -> f64 {
+        let share = |counts: &[u32], i: usize| {
+            let total: u32 = counts.iter().sum();
+            if total == 0 {
+                0.0
+            } else {
+                f64::from(counts[i]) / f64::from(total)
+            }
+        };
+        1.0 + self.weight
+            * (share(&history.hours, self.hour) + share(&history.weekdays, self.weekday))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 enum SourceType {
     StartMenu,
     Path,
+    /// One of the configured `index_dirs`
+    Folder,
     // todo: WinApp,
 }
 
@@ -30,244 +220,4736 @@ impl SourceType {
         match *self {
             SourceType::StartMenu => "S",
             SourceType::Path => "P",
+            SourceType::Folder => "F",
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct Program {
     title: String,
     source: SourceType,
     abs_path: String,
+    /// When the program first showed up in the index, 0 if it was there from the start
+    #[serde(default)]
+    first_seen: u64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum TieBreaker {
+    Alphabetical,
+    ShortestTitle,
+    /// Start Menu entries before PATH entries
+    Source,
+    /// Programs in shallower directories first
+    PathDepth,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct Config {
+    /// Folders indexed recursively in addition to the Start Menu and PATH
+    index_dirs: Vec<String>,
+    /// File extensions of programs to index
+    extensions: Vec<String>,
+    /// Glob patterns of paths or titles that are left out of the index
+    index_exclude: Vec<String>,
+    /// `wlines`, `fzf`, `rofi` or `dmenu`
+    frontend: String,
+    /// The frontend's executable, or empty for the one named like it in PATH
+    wlines_path: String,
+    /// Arguments passed to wlines before the ones from the command line
+    wlines_args: Vec<String>,
+    /// Arguments passed to wlines by a subcommand like `power`, or in a profile as `profile:<name>`
+    mode_args: HashMap<String, Vec<String>>,
+    /// wlines_run arguments started by global hotkeys of the daemon, like `Win+Alt+Space`
+    hotkeys: HashMap<String, Vec<String>>,
+    /// Absolute paths of programs that are always shown at the top of the menu
+    pinned: Vec<String>,
+    /// Keywords that can be typed to select a program, mapped to its absolute path or title
+    aliases: HashMap<String, String>,
+    /// Extra search terms for programs, keyed by absolute path or title
+    keywords: HashMap<String, Vec<String>>,
+    /// Add acronyms of multi-word titles as search terms, e.g. "vsc" for "Visual Studio Code"
+    acronyms: bool,
+    /// Show how many times each program has been launched
+    show_run_count: bool,
+    /// Prefix programs that failed to start the last time with `! `
+    mark_failed: bool,
+    /// List the programs launched today first, most recent first, above pins and frecency
+    launched_today_first: bool,
+    /// For how many days programs that are new in the index are highlighted, 0 to disable
+    new_program_days: u64,
+    /// Number of top entries that get a `1)`, `2)`, ... prefix for selecting them by typing the number
+    quick_select: usize,
+    /// Group programs without history by their Start Menu folder, under header lines
+    group_by_folder: bool,
+    /// How programs with equal or no frecency are ordered, with alphabetical as the final fallback
+    tie_breakers: Vec<TieBreaker>,
+    /// Boost programs usually launched at the current hour and weekday
+    context_ranking: bool,
+    /// How strongly the hour and weekday boost affects ranking
+    context_weight: f64,
+    /// Show the `S] `/`P] ` source prefix in front of each program
+    show_source: bool,
+    /// Programs that are always launched as administrator, by absolute path or title
+    run_as_admin: Vec<String>,
+    /// Typing this after the arguments opens the action menu, e.g. `?`
+    action_menu_suffix: String,
+    /// Working directories for programs, keyed by absolute path or title
+    working_dirs: HashMap<String, String>,
+    /// Environment variables set for programs, keyed by absolute path or title
+    environment: HashMap<String, HashMap<String, String>>,
+    /// Named argument presets for programs, keyed by absolute path or title
+    presets: HashMap<String, HashMap<String, String>>,
+    /// Terminal command console programs are started in, empty to start them directly
+    console_terminal: String,
+    /// Shell command run after each successful launch
+    post_launch_hook: String,
+    /// Initial window states for programs, keyed by absolute path or title
+    window_states: HashMap<String, WindowState>,
+    /// Ask before elevating programs whose manifest requires administrator
+    confirm_auto_elevate: bool,
+    /// Shell that typed text matching no entry can be run in, or empty to disable
+    fallback_shell: String,
+    /// Glob patterns of paths or titles that ask for confirmation before being launched
+    confirm: Vec<String>,
+    /// Ask before shutting down, restarting, sleeping or signing out from the power menu
+    confirm_power_actions: bool,
+    /// Keywords mapped to search URL templates, where `{}` is replaced by the query
+    search_engines: HashMap<String, String>,
+    /// Search URL template offered for input that matches nothing, or empty to disable
+    default_search_engine: String,
+    /// Everything's command line interface, used for file searches
+    everything_command: String,
+    /// How many results a file search shows at most
+    file_search_results: usize,
+    /// How many clips `clip --watch` keeps
+    clip_history_size: usize,
+    /// Named text for the `snippet` menu
+    snippets: HashMap<String, String>,
+    /// Terminal that `dirs --terminal` opens folders in
+    directory_terminal: String,
+    /// Play the notification sound when a timer is done
+    timer_sound: bool,
+    /// Text file that `note` appends to, or empty for `%AppData%\wlines_run_notes.txt`
+    notes_file: String,
+    /// Named resolutions and refresh rates for the `display` menu
+    display_presets: HashMap<String, DisplayPreset>,
+    /// `keepassxc` or `bitwarden`, for the `pass` menu
+    password_manager: String,
+    /// KeePassXC database that `pass` reads
+    keepassxc_database: String,
+    /// After how many seconds a copied password is cleared from the clipboard, 0 to keep it
+    password_clear_seconds: u64,
+    /// Named menus of titles mapped to command lines, opened with `menu:<name>` or by their name
+    menus: HashMap<String, HashMap<String, String>>,
+    /// How many programs the history keeps, dropping the lowest frecency ones, 0 for no limit
+    max_history_entries: usize,
+    /// Sum of all ranks above which they're scaled down, 0 to never age them
+    max_total_rank: u32,
+    /// Record launches in the history, which `--no-history` turns off for one run
+    record_history: bool,
+    /// Glob patterns of paths or titles that are never recorded in the history
+    history_exclude: Vec<String>,
+    /// Encrypt the history file for the current user with DPAPI
+    encrypt_history: bool,
+    /// Synced folder where each machine keeps its history, merged with the others for ranking
+    history_sync_dir: String,
+    /// Names of profiles with their own history, switched between from the command palette
+    profiles: Vec<String>,
+    /// Added to the score of programs matching glob patterns of their path or title, negative to
+    /// push them down
+    score_modifiers: HashMap<String, f64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            index_dirs: Vec::new(),
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            index_exclude: Vec::new(),
+            frontend: String::from("wlines"),
+            wlines_path: String::new(),
+            wlines_args: Vec::new(),
+            mode_args: HashMap::new(),
+            hotkeys: HashMap::new(),
+            pinned: Vec::new(),
+            aliases: HashMap::new(),
+            keywords: HashMap::new(),
+            acronyms: true,
+            show_run_count: false,
+            mark_failed: true,
+            launched_today_first: false,
+            new_program_days: 3,
+            quick_select: 0,
+            group_by_folder: false,
+            tie_breakers: vec![TieBreaker::Alphabetical],
+            context_ranking: false,
+            context_weight: 1.0,
+            show_source: true,
+            run_as_admin: Vec::new(),
+            action_menu_suffix: String::new(),
+            working_dirs: HashMap::new(),
+            environment: HashMap::new(),
+            presets: HashMap::new(),
+            console_terminal: String::from("cmd /s /k"),
+            post_launch_hook: String::new(),
+            window_states: HashMap::new(),
+            confirm_auto_elevate: false,
+            fallback_shell: String::from("cmd /c"),
+            confirm: Vec::new(),
+            confirm_power_actions: true,
+            search_engines: HashMap::new(),
+            default_search_engine: String::new(),
+            everything_command: String::from("es.exe"),
+            file_search_results: 500,
+            clip_history_size: 100,
+            snippets: HashMap::new(),
+            directory_terminal: String::from("cmd.exe"),
+            timer_sound: true,
+            notes_file: String::new(),
+            display_presets: HashMap::new(),
+            password_manager: String::new(),
+            keepassxc_database: String::new(),
+            password_clear_seconds: 30,
+            menus: HashMap::new(),
+            max_history_entries: 1000,
+            max_total_rank: 5000,
+            record_history: true,
+            history_exclude: Vec::new(),
+            encrypt_history: false,
+            history_sync_dir: String::new(),
+            profiles: Vec::new(),
+            score_modifiers: HashMap::new(),
+        }
+    }
+}
+
+// A file next to the executable that makes wlines_run keep its data there instead of in %AppData%
+const PORTABLE_MARKER: &str = "wlines_run.portable";
+
+// Portable mode, also set by `--portable`. As a variable it carries over to the processes that
+// wlines_run starts itself, like timers.
+const PORTABLE_VAR: &str = "WLINES_RUN_PORTABLE";
+
+fn data_dir() -> Result<String, Error> {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    match exe_dir {
+        Some(dir) if env::var_os(PORTABLE_VAR).is_some() || dir.join(PORTABLE_MARKER).exists() => {
+            Ok(dir.to_string_lossy().into_owned())
+        }
+        _ => env::var("AppData").map_err(|_| Error::NoDataDir),
+    }
+}
+
+// Nothing is written, also set by `--read-only`
+const READ_ONLY_VAR: &str = "WLINES_RUN_READ_ONLY";
+
+fn read_only() -> bool {
+    env::var_os(READ_ONLY_VAR).is_some()
 }
 
+// Paths of the index and history used instead of the ones in the data folder, also set by
+// `--index-file` and `--history-file`
+const INDEX_VAR: &str = "WLINES_RUN_INDEX";
+const HISTORY_VAR: &str = "WLINES_RUN_HISTORY";
+
 lazy_static! {
-    static ref INDEX_PATH: String = env::var("AppData").unwrap() + "/wlines_run_index.json";
-    static ref HISTORY_PATH: String = env::var("AppData").unwrap() + "/wlines_run_history.json";
+    static ref DATA_DIR: String = data_dir().or_exit();
+    static ref INDEX_PATH: String =
+        env::var(INDEX_VAR).unwrap_or_else(|_| DATA_DIR.clone() + "/wlines_run_index.json");
+    static ref HISTORY_PATH: String = DATA_DIR.clone() + "/wlines_run_history.json";
+    static ref CONFIG_PATH: String = DATA_DIR.clone() + "/wlines_run_config.json";
+    static ref LOG_PATH: String = DATA_DIR.clone() + "/wlines_run.log";
+    static ref CONFIG_TOML_PATH: String = DATA_DIR.clone() + "/wlines_run/config.toml";
+    static ref PINS_PATH: String = DATA_DIR.clone() + "/wlines_run_pins.json";
+    static ref BLACKLIST_PATH: String = DATA_DIR.clone() + "/wlines_run_blacklist.json";
+    static ref USERS_PATH: String = DATA_DIR.clone() + "/wlines_run_users.json";
+    static ref FILES_PATH: String = DATA_DIR.clone() + "/wlines_run_files.json";
+    static ref DIRS_PATH: String = DATA_DIR.clone() + "/wlines_run_dirs.json";
+    static ref CLIPS_PATH: String = DATA_DIR.clone() + "/wlines_run_clips.json";
+    static ref PROFILE_PATH: String = DATA_DIR.clone() + "/wlines_run_profile.txt";
+    static ref TIMERS_PATH: String = DATA_DIR.clone() + "/wlines_run_timers.json";
+}
+
+const DEFAULT_EXTENSIONS: &[&str] = &["exe", "lnk", "bat", "cmd", "com"];
+
+// Our own executable, for starting background processes like timers
+fn own_exe() -> Result<String, Error> {
+    env::current_exe()
+        .map(|exe| exe.to_string_lossy().into_owned())
+        .map_err(|source| Error::Io {
+            action: "find the wlines_run executable",
+            source,
+        })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
 
-const EXTENSIONS: &'static [&'static str] = &["exe", "lnk", "bat", "cmd", "com"];
+// Unix time of the last local midnight
+fn start_of_day() -> u64 {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map_or(0, |midnight| midnight.timestamp() as u64)
+}
 
 fn frecency(history: &HistoryEntry, current_time: u64) -> f64 {
     (history.rank as f64) / (((current_time as f64) - (history.access as f64)).sqrt() / 10.0 + 5.0)
+        * FAILURE_PENALTY.powi(history.failures as i32)
 }
 
-fn index_directory(
-    programs: &mut HashMap<String, Program>,
-    dir: &Path,
-    prefix: &Path,
-    source: &SourceType,
-    recursively: bool,
-) {
-    if let Ok(rd) = fs::read_dir(dir) {
-        for entry in rd {
-            let path = entry.unwrap().path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if EXTENSIONS.iter().any(|&e| e == ext) {
-                        let title_str =
-                            String::from(path.strip_prefix(prefix).unwrap().to_str().unwrap());
-                        let path_str = String::from(path.to_str().unwrap());
-                        programs.insert(
-                            path_str.to_ascii_lowercase(),
-                            Program {
-                                title: title_str,
-                                abs_path: path_str,
-                                source: *source,
-                            },
-                        );
-                    }
-                }
-            } else if path.is_dir() && recursively {
-                let _ = index_directory(programs, &path, prefix, source, true);
+// Programs with a score come first, highest first, except that ones penalized below zero go
+// after the ones without a score
+fn compare_scores(
+    a: &Program,
+    b: &Program,
+    a_score: Option<f64>,
+    b_score: Option<f64>,
+    tie_breakers: &[TieBreaker],
+) -> Ordering {
+    let class = |score: Option<f64>| match score {
+        Some(score) if score >= 0.0 => 0,
+        None => 1,
+        Some(_) => 2,
+    };
+    class(a_score)
+        .cmp(&class(b_score))
+        .then_with(|| match (a_score, b_score) {
+            (Some(a_score), Some(b_score)) => {
+                b_score.partial_cmp(&a_score).unwrap_or(Ordering::Equal)
             }
-        }
+            _ => Ordering::Equal,
+        })
+        .then_with(|| break_tie(a, b, tie_breakers))
+}
+
+fn break_tie(a: &Program, b: &Program, tie_breakers: &[TieBreaker]) -> Ordering {
+    let title_length = |program: &Program| {
+        Path::new(&program.title)
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .map_or(0, |name| name.chars().count())
+    };
+    let source_priority = |program: &Program| match program.source {
+        SourceType::StartMenu => 0,
+        SourceType::Folder => 1,
+        SourceType::Path => 2,
+    };
+    let path_depth = |program: &Program| Path::new(&program.abs_path).components().count();
+
+    tie_breakers
+        .iter()
+        .map(|tie_breaker| match tie_breaker {
+            TieBreaker::Alphabetical => collate(&a.title, &b.title),
+            TieBreaker::ShortestTitle => title_length(a).cmp(&title_length(b)),
+            TieBreaker::Source => source_priority(a).cmp(&source_priority(b)),
+            TieBreaker::PathDepth => path_depth(a).cmp(&path_depth(b)),
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| collate(&a.title, &b.title))
+}
+
+// Case-insensitive comparison following the sort order of the user's locale
+#[cfg(windows)]
+fn collate(a: &str, b: &str) -> Ordering {
+    use std::ptr;
+    use winapi::um::stringapiset::CompareStringEx;
+
+    const LINGUISTIC_IGNORECASE: u32 = 0x10;
+    const SORT_DIGITSASNUMBERS: u32 = 0x8;
+
+    let a_wide: Vec<u16> = a.encode_utf16().collect();
+    let b_wide: Vec<u16> = b.encode_utf16().collect();
+    let result = unsafe {
+        CompareStringEx(
+            ptr::null(), // LOCALE_NAME_USER_DEFAULT
+            LINGUISTIC_IGNORECASE | SORT_DIGITSASNUMBERS,
+            a_wide.as_ptr(),
+            a_wide.len() as i32,
+            b_wide.as_ptr(),
+            b_wide.len() as i32,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+        )
+    };
+    match result {
+        1 => Ordering::Less,
+        3 => Ordering::Greater,
+        2 => a.cmp(b),
+        _ => a
+            .to_lowercase()
+            .cmp(&b.to_lowercase())
+            .then_with(|| a.cmp(b)),
     }
 }
 
-fn index_start_menu(programs: &mut HashMap<String, Program>) {
-    const PROG_DIR: &'static str = "/Microsoft/Windows/Start Menu/Programs";
+#[cfg(not(windows))]
+fn collate(a: &str, b: &str) -> Ordering {
+    let fold = |s: &str| fold_diacritics(s).to_lowercase();
+    fold(a).cmp(&fold(b)).then_with(|| a.cmp(b))
+}
 
-    let path_str = env::var("AppData").unwrap() + PROG_DIR;
-    let path = Path::new(&path_str);
-    index_directory(programs, path, path, &SourceType::StartMenu, true);
+// Indexed programs, without the ones hidden since the index was built
+fn try_load_index() -> Result<Vec<Program>, Error> {
+    let index_json_data = match fs::read_to_string(&*INDEX_PATH) {
+        Ok(index_json_data) => index_json_data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Err(Error::IndexMissing),
+        Err(source) => {
+            return Err(Error::Read {
+                path: INDEX_PATH.clone(),
+                source,
+            })
+        }
+    };
+    let mut programs: Vec<Program> = parse_json(&INDEX_PATH, &index_json_data)?;
 
-    let path_str = env::var("ProgramData").unwrap() + PROG_DIR;
-    let path = Path::new(&path_str);
-    index_directory(programs, path, path, &SourceType::StartMenu, true);
+    let hidden = load_hidden()?;
+    programs.retain(|program| !hidden.contains(&program.abs_path.to_ascii_lowercase()));
+    Ok(programs)
 }
 
-fn index_path(programs: &mut HashMap<String, Program>) {
-    for path in env::split_paths(&env::var("PATH").unwrap()) {
-        index_directory(programs, &path, &path, &SourceType::Path, false);
-    }
+fn load_index() -> Vec<Program> {
+    try_load_index().or_exit()
 }
 
-fn cmd_index() {
-    // When indexing, use lowercase program path as a key to prevent some duplicates
-    let mut programs: HashMap<String, Program> = HashMap::new();
-    index_start_menu(&mut programs);
-    index_path(&mut programs);
+// The profile switched to from the command palette, empty for the default one
+fn active_profile() -> String {
+    fs::read_to_string(&*PROFILE_PATH)
+        .map(|profile| profile.trim().to_string())
+        .unwrap_or_default()
+}
 
-    // Collect into vector since we don't need the dictionary structure anymore
-    let programs: Vec<&Program> = programs.values().collect();
+fn machine_name() -> String {
+    env::var("COMPUTERNAME").unwrap_or_else(|_| String::from("local"))
+}
 
-    // Write to file
-    let index_json_data = serde_json::to_string_pretty(&programs).unwrap();
-    fs::write(&*INDEX_PATH, index_json_data).expect("Unable to write to wlines_run_index.json");
-    println!("Indexed {} programs", programs.len());
+// Each profile has its own history, so that launches in one context don't affect another. In the
+// `history_sync_dir` each machine writes a file of its own, e.g. `wlines_run_history.DESKTOP.json`,
+// so that the sync never has conflicting changes to resolve.
+fn history_path(profile: &str, sync_dir: &str) -> String {
+    let name = if profile.is_empty() {
+        String::from("wlines_run_history")
+    } else {
+        String::from("wlines_run_history_") + profile
+    };
+    if !sync_dir.is_empty() {
+        format!("{}/{}.{}.json", sync_dir, name, machine_name())
+    } else if profile.is_empty() {
+        HISTORY_PATH.clone()
+    } else {
+        DATA_DIR.clone() + "/" + &name + ".json"
+    }
 }
 
-fn format_program_display_name(program: &Program) -> String {
-    format!("{}] ", program.source.display_name()) + &program.title
+fn default_history_path() -> String {
+    env::var(HISTORY_VAR)
+        .unwrap_or_else(|_| history_path(&active_profile(), &load_config().history_sync_dir))
 }
 
-fn cmd_run(wlines_args: Vec<String>) {
-    // Start wlines right away
-    let mut wlines = Command::new("wlines")
-        .args(wlines_args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Couldn't start wlines");
-
-    // Load index
-    let index_json_data =
-        fs::read_to_string(&*INDEX_PATH).expect("Unable to load wlines_run_index.json");
-    let mut programs: Vec<Program> = serde_json::from_str(&index_json_data).unwrap();
-    println!("Loaded {} indexed programs", programs.len());
-
-    // Load history
-    let mut history: HashMap<String, HistoryEntry>;
-    if let Ok(history_json_data) = fs::read_to_string(&*HISTORY_PATH) {
-        history = serde_json::from_str(&history_json_data).unwrap();
-        println!("Loaded history");
+// An encrypted history is read whether or not `encrypt_history` is still set, so turning it off
+// decrypts the history on the next save
+fn parse_history(mut history_json_data: Vec<u8>) -> io::Result<HashMap<String, HistoryEntry>> {
+    if history_json_data.first() != Some(&b'{') {
+        history_json_data = dpapi::unprotect(&history_json_data)?;
+    }
+    serde_json::from_slice(&history_json_data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn try_load_history(path: &str) -> Result<HashMap<String, HistoryEntry>, Error> {
+    if let Ok(history_json_data) = fs::read(path) {
+        parse_history(history_json_data).map_err(|source| Error::Read {
+            path: path.to_string(),
+            source,
+        })
     } else {
-        history = HashMap::new();
+        Ok(HashMap::new())
     }
+}
 
-    // Sort programs by frecency
-    let time_now: u64 = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    programs.sort_by(|a, b| {
-        if history.contains_key(&a.abs_path) && history.contains_key(&b.abs_path) {
-            let a_score = frecency(&history[&a.abs_path], time_now);
-            let b_score = frecency(&history[&b.abs_path], time_now);
-            b_score.partial_cmp(&a_score).unwrap()
-        } else if history.contains_key(&a.abs_path) {
-            Ordering::Less
-        } else if history.contains_key(&b.abs_path) {
-            Ordering::Greater
-        } else {
-            a.title.cmp(&b.title)
+fn load_history(path: &str) -> HashMap<String, HistoryEntry> {
+    try_load_history(path).or_exit()
+}
+
+// The files that other machines wrote next to this machine's history in the `history_sync_dir`
+fn synced_history_paths(path: &str) -> Vec<PathBuf> {
+    let path = Path::new(path);
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let prefix = match file_name.strip_suffix(&format!(".{}.json", machine_name())) {
+        Some(name) => format!("{}.", name),
+        None => return Vec::new(),
+    };
+    let entries = match path.parent().map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|other| {
+            let other_name = other.file_name().unwrap_or_default().to_string_lossy();
+            other_name != file_name
+                && other_name
+                    .strip_prefix(&prefix)
+                    .and_then(|rest| rest.strip_suffix(".json"))
+                    .is_some_and(|machine| !machine.is_empty() && !machine.contains('.'))
+        })
+        .collect()
+}
+
+// This machine's history together with the other machines' ones, for ranking. Ranks and launch
+// times are summed and the latest access is kept, while launches are only recorded in this
+// machine's file.
+fn merge_synced_histories(
+    path: &str,
+    history: &HashMap<String, HistoryEntry>,
+) -> HashMap<String, HistoryEntry> {
+    let mut merged = history.clone();
+    for other_path in synced_history_paths(path) {
+        // Another machine's encrypted history can't be decrypted here
+        let other = match fs::read(&other_path).and_then(parse_history) {
+            Ok(other) => other,
+            Err(err) => {
+                log::warn!("Skipping {}: {}", other_path.display(), err);
+                continue;
+            }
+        };
+        for (program, entry) in &other {
+            merged.entry(program.clone()).or_default().merge(entry);
         }
-    });
+    }
+    merged
+}
 
-    // Create display names for each program that link back to each program
-    let prog_name_links: Vec<(String, &Program)> = programs
+// Drop the lowest frecency entries beyond `max_entries`, so that the history of one-off launches
+// doesn't grow forever
+fn cap_history(history: &mut HashMap<String, HistoryEntry>, max_entries: usize, current_time: u64) {
+    if max_entries == 0 || history.len() <= max_entries {
+        return;
+    }
+    let mut scores: Vec<(f64, String)> = history
         .iter()
-        .map(|program| (format_program_display_name(program), program))
+        .map(|(path, entry)| (frecency(entry, current_time), path.clone()))
         .collect();
-
-    // Send to wlines
-    {
-        let prog_names_str = prog_name_links
-            .iter()
-            .fold(String::new(), |acc, prog_name_link| {
-                acc + &prog_name_link.0 + ": \n"
-            });
-
-        let stdin = wlines.stdin.as_mut().unwrap();
-        stdin
-            .write_all(prog_names_str.as_bytes())
-            .expect("Couldn't communicate with wlines stdin");
+    scores.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    let excess = history.len() - max_entries;
+    for (_, path) in scores.into_iter().take(excess) {
+        history.remove(&path);
     }
+}
 
-    // Wait for output
-    // todo: allow for multiple inputs from the same menu (ctrl+enter)
-    let output = wlines
-        .wait_with_output()
-        .expect("Failed to read wlines output");
-    if !output.status.success() {
-        println!("Exited\n");
+// Like z, when the ranks add up to more than `max_total_rank` they're all scaled down, and entries
+// that reach zero are forgotten. Programs that stopped being used then drop out over time, even
+// though ranks otherwise only grow.
+fn age_history(history: &mut HashMap<String, HistoryEntry>, max_total_rank: u32) {
+    if max_total_rank == 0 {
         return;
     }
+    let total: u64 = history.values().map(|entry| u64::from(entry.rank)).sum();
+    if total <= u64::from(max_total_rank) {
+        return;
+    }
+    for entry in history.values_mut() {
+        entry.rank = (f64::from(entry.rank) * RANK_AGING_FACTOR) as u32;
+    }
+    history.retain(|_, entry| entry.rank > 0);
+}
 
-    // Match selection
-    let input_string = String::from_utf8(output.stdout).unwrap().trim().to_string();
-    let matched_input = prog_name_links
+// Forget programs that were uninstalled or moved since they were launched. Only entries that
+// aren't in `indexed` are looked up on disk, and ones that aren't absolute paths, such as URLs or
+// commands launched with `launch`, are kept.
+fn drop_missing_programs(
+    history: &mut HashMap<String, HistoryEntry>,
+    indexed: &[Program],
+) -> usize {
+    let indexed: HashSet<&str> = indexed
         .iter()
-        .find(|&prog_name_link| input_string.starts_with(&format!("{}:", prog_name_link.0)));
-    let chosen_prog = if let Some(x) = matched_input {
-        x
+        .map(|program| program.abs_path.as_str())
+        .collect();
+    let before = history.len();
+    history.retain(|path, _| {
+        let path_ref = Path::new(path);
+        indexed.contains(path.as_str()) || !path_ref.is_absolute() || path_ref.exists()
+    });
+    before - history.len()
+}
+
+// Applied whenever a launch is recorded
+fn tidy_history(config: &Config, history: &mut HashMap<String, HistoryEntry>, current_time: u64) {
+    age_history(history, config.max_total_rank);
+    cap_history(history, config.max_history_entries, current_time);
+}
+
+// Held while a history is read, changed and written, so that instances started at nearly the same
+// time don't overwrite each other's launches
+struct HistoryLock {
+    lock_path: String,
+}
+
+impl Drop for HistoryLock {
+    fn drop(&mut self) {
+        if !read_only() {
+            let _ = fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+fn try_lock_history(path: &str) -> Result<HistoryLock, Error> {
+    let lock_path = format!("{}.lock", path);
+    // Nothing is saved, so there's nothing to keep other instances from overwriting
+    if read_only() {
+        return Ok(HistoryLock { lock_path });
+    }
+    loop {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(_) => return Ok(HistoryLock { lock_path }),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                // A lock left behind by an instance that was killed while saving
+                let stale = fs::metadata(&lock_path)
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| {
+                        modified.elapsed().unwrap_or_default() > std::time::Duration::from_secs(10)
+                    });
+                if stale {
+                    let _ = fs::remove_file(&lock_path);
+                } else {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                }
+            }
+            Err(source) => {
+                return Err(Error::Write {
+                    path: lock_path,
+                    source,
+                })
+            }
+        }
+    }
+}
+
+fn lock_history(path: &str) -> HistoryLock {
+    try_lock_history(path).or_exit()
+}
+
+// Apply the changes from `original` to `changed` to the history as it is on disk now, which
+// another instance may have saved since `original` was loaded
+fn apply_history_changes(
+    current: &mut HashMap<String, HistoryEntry>,
+    original: &HashMap<String, HistoryEntry>,
+    changed: &HashMap<String, HistoryEntry>,
+) {
+    for path in original.keys() {
+        if !changed.contains_key(path) {
+            current.remove(path);
+        }
+    }
+    let empty = HistoryEntry::default();
+    for (path, entry) in changed {
+        let original_entry = original.get(path);
+        if original_entry == Some(entry) {
+            continue;
+        }
+        current
+            .entry(path.clone())
+            .or_default()
+            .apply_changes(original_entry.unwrap_or(&empty), entry);
+    }
+}
+
+fn try_save_history(
+    path: &str,
+    history: &HashMap<String, HistoryEntry>,
+    encrypt: bool,
+) -> Result<(), Error> {
+    let mut history_json_data = serde_json::to_vec_pretty(history).unwrap();
+    if encrypt {
+        history_json_data = dpapi::protect(&history_json_data).map_err(|source| Error::Write {
+            path: path.to_string(),
+            source,
+        })?;
+    }
+    try_write_data(path, history_json_data)
+}
+
+fn save_history(path: &str, history: &HashMap<String, HistoryEntry>, encrypt: bool) {
+    try_save_history(path, history, encrypt).or_exit()
+}
+
+// The TOML configuration is used when it exists, otherwise the JSON one
+fn try_load_config() -> Result<Config, Error> {
+    let config: Config = if let Ok(config_toml_data) = fs::read_to_string(&*CONFIG_TOML_PATH) {
+        toml::from_str(&config_toml_data).map_err(|err| Error::Parse {
+            path: CONFIG_TOML_PATH.clone(),
+            message: err.message().to_string(),
+        })?
+    } else if let Ok(config_json_data) = fs::read_to_string(&*CONFIG_PATH) {
+        parse_json(&CONFIG_PATH, &config_json_data)?
     } else {
-        println!("Unknown choice '{}'\n", input_string);
-        return;
+        Config::default()
     };
+    // Otherwise an unknown frontend would only be noticed once a menu is shown
+    load_frontend(&config)?;
+    Ok(config)
+}
 
-    // Extract input arguments
-    let mut prog_args: Vec<String> = Vec::new();
-    if input_string.len() > chosen_prog.0.len() + 1 {
-        // + 1 to compensate `:` suffix
-        let arg_string = input_string[(chosen_prog.0.len() + 1)..].to_string();
-        prog_args = shlex::split(&arg_string).unwrap();
+fn load_config() -> Config {
+    if let Some(watcher) = CONFIG_WATCHER.lock().unwrap().as_mut() {
+        return watcher.get().clone();
     }
+    try_load_config().or_exit()
+}
 
-    // Launch it
-    println!("Starting \"{}\"\n", chosen_prog.1.abs_path);
-    let mut launch_args: Vec<String> = vec![
-        String::from("/c"),
-        String::from("start"),
-        String::from(""),
-        chosen_prog.1.abs_path.clone(),
-    ];
-    launch_args.append(&mut prog_args);
-    Command::new("cmd")
-        .args(launch_args)
-        .spawn()
-        .expect("Couldn't start program");
+fn config_modified() -> Option<SystemTime> {
+    fs::metadata(&*CONFIG_TOML_PATH)
+        .or_else(|_| fs::metadata(&*CONFIG_PATH))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
 
-    // Save to history
-    match history.get_mut(&chosen_prog.1.abs_path) {
-        Some(entry) => {
-            entry.rank += 1;
-            entry.access = time_now;
+// The configuration file in use, for opening it in an editor. The JSON one is only used when
+// there's no TOML one, and new ones are TOML, which is created empty here.
+fn editable_config_path() -> Result<&'static str, Error> {
+    let path = if !Path::new(&*CONFIG_TOML_PATH).exists() && Path::new(&*CONFIG_PATH).exists() {
+        CONFIG_PATH.as_str()
+    } else {
+        CONFIG_TOML_PATH.as_str()
+    };
+    if !Path::new(path).exists() {
+        if let Some(dir) = Path::new(path).parent() {
+            let _ = fs::create_dir_all(dir);
         }
-        None => {
-            history.insert(
-                chosen_prog.1.abs_path.to_string(),
-                HistoryEntry {
-                    rank: 1,
-                    access: time_now,
-                },
-            );
+        try_write_data(path, "")?;
+    }
+    Ok(path)
+}
+
+// The configuration of a process that keeps running, reloaded when the file changes. A change
+// that doesn't parse is reported and the previous configuration is kept until it's fixed.
+struct ConfigWatcher {
+    config: Config,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    fn new() -> ConfigWatcher {
+        ConfigWatcher {
+            modified: config_modified(),
+            config: load_config(),
+        }
+    }
+
+    fn get(&mut self) -> &Config {
+        let modified = config_modified();
+        if modified != self.modified {
+            self.modified = modified;
+            match try_load_config() {
+                Ok(config) => {
+                    self.config = config;
+                    log::info!("Reloaded the configuration");
+                }
+                Err(err) => {
+                    log::warn!("{}, keeping the previous configuration", err);
+                    // Showing the notification blocks for as long as it's shown
+                    std::thread::spawn(move || {
+                        let _ = notify::show("wlines_run", &err.to_string(), false);
+                    });
+                }
+            }
         }
+        &self.config
     }
+}
 
-    let history_json_data = serde_json::to_string_pretty(&history).unwrap();
-    fs::write(&*HISTORY_PATH, history_json_data)
-        .expect("Unable to write to wlines_run_history.json");
+// Set by processes that keep running, whose `load_config` then goes through the watcher
+static CONFIG_WATCHER: std::sync::Mutex<Option<ConfigWatcher>> = std::sync::Mutex::new(None);
+
+fn watch_config() {
+    let watcher = ConfigWatcher::new();
+    *CONFIG_WATCHER.lock().unwrap() = Some(watcher);
 }
 
-fn usage() {
-    eprintln!("wlines_run <index|run [args...]>");
-    process::exit(1);
+fn parse_json<T: DeserializeOwned>(path: &str, json_data: &str) -> Result<T, Error> {
+    serde_json::from_str(json_data).map_err(|err| Error::Parse {
+        path: path.to_string(),
+        message: err.to_string(),
+    })
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        usage();
+// A data file, or the default value if it doesn't exist yet
+fn try_load_data<T: DeserializeOwned + Default>(path: &str) -> Result<T, Error> {
+    match fs::read_to_string(path) {
+        Ok(json_data) => parse_json(path, &json_data),
+        Err(_) => Ok(T::default()),
+    }
+}
+
+fn load_data<T: DeserializeOwned + Default>(path: &str) -> T {
+    try_load_data(path).or_exit()
+}
+
+fn try_write_data(path: &str, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    if read_only() {
+        log::debug!("Not writing {} in read-only mode", path);
+        return Ok(());
+    }
+    fs::write(path, contents).map_err(|source| Error::Write {
+        path: path.to_string(),
+        source,
+    })
+}
+
+fn write_data(path: &str, contents: impl AsRef<[u8]>) {
+    try_write_data(path, contents).or_exit()
+}
+
+// Lists (pins, blacklist, users) are stored as JSON arrays of strings
+fn load_list(list_path: &str) -> Result<Vec<String>, Error> {
+    try_load_data(list_path)
+}
+
+fn add_to_list(list_path: &str, item: &str) -> Result<(), Error> {
+    let mut list = load_list(list_path)?;
+    if !list.iter().any(|i| i.eq_ignore_ascii_case(item)) {
+        list.push(item.to_string());
+        let list_json_data = serde_json::to_string_pretty(&list).unwrap();
+        try_write_data(list_path, list_json_data)?;
     }
+    Ok(())
+}
+
+fn remove_from_list(list_path: &str, item: &str) -> Result<(), Error> {
+    let mut list = load_list(list_path)?;
+    list.retain(|i| !i.eq_ignore_ascii_case(item));
+    let list_json_data = serde_json::to_string_pretty(&list).unwrap();
+    try_write_data(list_path, list_json_data)
+}
+
+fn load_hidden() -> Result<HashSet<String>, Error> {
+    Ok(load_list(&BLACKLIST_PATH)?
+        .iter()
+        .map(|path| path.to_ascii_lowercase())
+        .collect())
+}
 
-    if args[1] == "index" {
-        cmd_index();
-    } else if args[1] == "run" {
-        cmd_run(args[2..].to_vec());
+// What a program points to, which is the program itself unless it is a shortcut
+fn program_target(program: &Program) -> String {
+    if shortcut::is_shortcut(&program.abs_path) {
+        shortcut::resolve_target(&program.abs_path).unwrap_or_else(|| program.abs_path.clone())
     } else {
-        usage();
+        program.abs_path.clone()
+    }
+}
+
+// Configured working directory, or the program's own folder. Shortcuts carry their own
+fn program_working_dir(config: &Config, program: &Program) -> Option<String> {
+    if let Some((_, dir)) = config
+        .working_dirs
+        .iter()
+        .find(|(target, _)| program_matches(program, target))
+    {
+        return Some(dir.clone());
+    }
+    if shortcut::is_shortcut(&program.abs_path) {
+        return None;
+    }
+    Path::new(&program.abs_path)
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .map(String::from)
+}
+
+// Pick one of the program's argument presets in a second menu
+fn choose_preset(
+    wlines_args: &[String],
+    config: &Config,
+    program: &Program,
+) -> Result<Option<String>, Error> {
+    let mut presets: Vec<(&String, &String)> = config
+        .presets
+        .iter()
+        .filter(|(target, _)| program_matches(program, target))
+        .flat_map(|(_, presets)| presets.iter())
+        .collect();
+    if presets.is_empty() {
+        log::info!("No presets for \"{}\"", program.title);
+        return Ok(None);
+    }
+    presets.sort();
+
+    let lines: Vec<String> = presets
+        .iter()
+        .map(|(name, args)| format!("{}: {}", name, args))
+        .collect();
+    let choice = match try_prompt(wlines_args, &lines)? {
+        Some(choice) => choice,
+        None => return Ok(None),
+    };
+    match lines.iter().position(|line| *line == choice) {
+        Some(i) => Ok(Some(presets[i].1.clone())),
+        None => {
+            log::info!("Unknown preset '{}'", choice);
+            Ok(None)
+        }
+    }
+}
+
+// Console programs would otherwise flash a window that closes as soon as they exit.
+// Shortcuts and interactive shells already open a usable window by themselves.
+fn runs_in_terminal(config: &Config, program: &Program) -> bool {
+    const SHELLS: &[&str] = &[
+        "cmd.exe",
+        "powershell.exe",
+        "pwsh.exe",
+        "bash.exe",
+        "wsl.exe",
+    ];
+
+    let file_name = Path::new(&program.abs_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    !config.console_terminal.is_empty()
+        && !shortcut::is_shortcut(&program.abs_path)
+        && !SHELLS.contains(&file_name.as_str())
+        && pe::is_console_program(&program.abs_path)
+}
+
+// Entries of the action menu and the action each one runs
+const ACTION_MENU: &[(&str, &str)] = &[
+    ("Run", "run"),
+    ("Run as administrator", "admin"),
+    ("Run as another user", "user"),
+    ("Run with preset", "preset"),
+    ("Run with previous arguments", "args"),
+    ("Open a file with it", "file"),
+    ("Edit", "edit"),
+    ("Print", "print"),
+    ("Open folder", "dir"),
+    ("Copy path", "copy"),
+    ("Pin", "pin"),
+    ("Unpin", "unpin"),
+    ("Hide", "hide"),
+    ("Uninstall", "uninstall"),
+];
+
+// Internal actions offered for input starting with `>`, e.g. `>` alone or `>reindex`
+const PALETTE: &[(&str, &str)] = &[
+    ("Reindex", "reindex"),
+    ("Edit config", "config"),
+    ("Open history file", "history"),
+    ("Prune stale entries", "prune"),
+    ("Switch profile", "profile"),
+    ("Show stats", "stats"),
+];
+
+// Entries of the `power` menu and the `shutdown.exe` arguments they run, if any
+const POWER_MENU: &[(&str, Option<&str>)] = &[
+    ("Shutdown", Some("/s /t 0")),
+    ("Restart", Some("/r /t 0")),
+    ("Sleep", None),
+    ("Hibernate", Some("/h")),
+    ("Lock", None),
+    ("Sign out", Some("/l")),
+    ("Restart to UEFI firmware settings", Some("/r /fw /t 0")),
+];
+
+// Emoji with their names and keywords, separated by tabs
+const EMOJI: &str = include_str!("emoji.txt");
+
+// Code points and names of arrows, math symbols, box drawing and other symbols
+const SYMBOLS: &str = include_str!("symbols.txt");
+
+// Clips longer than this many characters are shortened in the clipboard history menu
+const MAX_CLIP_LINE: usize = 200;
+
+// Projection modes offered by the display menu, in the order of the Win+P flyout
+const DISPLAY_MENU: &[(&str, Topology)] = &[
+    ("PC screen only", Topology::Internal),
+    ("Duplicate", Topology::Clone),
+    ("Extend", Topology::Extend),
+    ("Second screen only", Topology::External),
+];
+
+// How many of the newest Windows "Recent items" are offered in the file picker
+const MAX_RECENT_ITEMS: usize = 50;
+
+fn load_file_history() -> Result<HashMap<String, HistoryEntry>, Error> {
+    try_load_data(&FILES_PATH)
+}
+
+fn record_file(path: &str, current_time: u64) -> Result<(), Error> {
+    let mut files = load_file_history()?;
+    files
+        .entry(path.to_string())
+        .or_default()
+        .record_launch(current_time);
+    let files_json_data = serde_json::to_string(&files).unwrap();
+    try_write_data(&FILES_PATH, files_json_data)
+}
+
+// Targets of the newest shortcuts in the Windows "Recent items" folder
+fn windows_recent_items() -> Vec<String> {
+    let recent_dir = match env::var("AppData") {
+        Ok(dir) => dir + "/Microsoft/Windows/Recent",
+        Err(_) => return Vec::new(),
+    };
+    let mut shortcuts: Vec<(SystemTime, PathBuf)> = match fs::read_dir(recent_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .filter(|(_, path)| shortcut::is_shortcut(&path.to_string_lossy()))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    shortcuts.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    shortcuts
+        .iter()
+        .take(MAX_RECENT_ITEMS)
+        .filter_map(|(_, path)| shortcut::resolve_target(&path.to_string_lossy()))
+        .filter(|target| Path::new(target).is_file())
+        .collect()
+}
+
+// Pick a file to open a program with: the ones opened this way before by frecency, then the
+// recent items of Windows. A path can also be typed.
+fn choose_file(wlines_args: &[String], current_time: u64) -> Result<Option<String>, Error> {
+    let files = load_file_history()?;
+    let mut lines: Vec<String> = files.keys().cloned().collect();
+    lines.sort_by(|a, b| {
+        frecency(&files[b], current_time)
+            .partial_cmp(&frecency(&files[a], current_time))
+            .unwrap_or(Ordering::Equal)
+    });
+    for item in windows_recent_items() {
+        if !lines.iter().any(|line| line.eq_ignore_ascii_case(&item)) {
+            lines.push(item);
+        }
+    }
+    let file = match try_prompt(wlines_args, &lines)? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let file = file.trim_matches('"');
+    if file.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(file.to_string()))
+}
+
+// Show an error in wlines, since there's usually no console to see it in when started from a hotkey
+fn report_error(wlines_args: &[String], message: &str) {
+    log::error!("{}", message);
+    if let Err(err) = try_prompt(wlines_args, &[message.to_string()]) {
+        log::error!("{}", err);
+    }
+}
+
+fn run_action(wlines_args: &[String], action: &str, program: &Program) -> Result<(), Error> {
+    match action {
+        "pin" => {
+            add_to_list(&PINS_PATH, &program.abs_path)?;
+            log::info!("Pinned \"{}\"", program.abs_path);
+        }
+        "unpin" => {
+            remove_from_list(&PINS_PATH, &program.abs_path)?;
+            log::info!("Unpinned \"{}\"", program.abs_path);
+        }
+        "dir" => {
+            // Shortcuts are opened next to what they point to rather than in the Start Menu
+            let path = program_target(program);
+            log::info!("Opening folder of \"{}\"", path);
+            match launch::open_folder(&path) {
+                Ok(()) => {
+                    if let Some(folder) = Path::new(&path).parent().and_then(|f| f.to_str()) {
+                        record_dir(folder, unix_time())?;
+                    }
+                }
+                Err(err) => report_error(
+                    wlines_args,
+                    &format!("Couldn't open folder of \"{}\": {}", path, err),
+                ),
+            }
+        }
+        "copy" => match clipboard::set_text(&program.abs_path) {
+            Ok(()) => log::info!("Copied \"{}\"", program.abs_path),
+            Err(err) => report_error(wlines_args, &format!("Couldn't copy to clipboard: {}", err)),
+        },
+        "hide" => {
+            add_to_list(&BLACKLIST_PATH, &program.abs_path)?;
+            log::info!("Hid \"{}\"", program.abs_path);
+        }
+        "uninstall" => match uninstall::find(&program_target(program), &program.title) {
+            Some(uninstaller) => {
+                log::info!("Uninstalling \"{}\"", uninstaller.name);
+                if let Err(err) =
+                    launch::run_command_line(&uninstaller.command_line, &LaunchOptions::default())
+                {
+                    report_error(
+                        wlines_args,
+                        &format!(
+                            "Couldn't start uninstaller of \"{}\": {}",
+                            uninstaller.name, err
+                        ),
+                    );
+                }
+            }
+            None => log::info!("No uninstaller found for \"{}\"", program.title),
+        },
+        _ => log::info!("Unknown action '{}'", action),
+    }
+    Ok(())
+}
+
+fn index_directory(
+    programs: &mut HashMap<String, Program>,
+    dir: &Path,
+    prefix: &Path,
+    source: &SourceType,
+    recursively: bool,
+    extensions: &[String],
+) {
+    if let Ok(rd) = fs::read_dir(dir) {
+        for entry in rd.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_file() {
+                if let Some(ext) = path.extension() {
+                    if extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)) {
+                        let title = path.strip_prefix(prefix).ok().and_then(Path::to_str);
+                        let (title_str, path_str) = match (title, path.to_str()) {
+                            (Some(title), Some(path)) => (String::from(title), String::from(path)),
+                            _ => {
+                                log::warn!(
+                                    "Skipping \"{}\", its name isn't valid Unicode",
+                                    path.display()
+                                );
+                                continue;
+                            }
+                        };
+                        programs.insert(
+                            path_str.to_ascii_lowercase(),
+                            Program {
+                                title: title_str,
+                                abs_path: path_str,
+                                source: *source,
+                                first_seen: 0,
+                            },
+                        );
+                    }
+                }
+            } else if path.is_dir() && recursively {
+                index_directory(programs, &path, prefix, source, true, extensions);
+            }
+        }
+    }
+}
+
+fn index_start_menu(programs: &mut HashMap<String, Program>, extensions: &[String]) {
+    const PROG_DIR: &str = "/Microsoft/Windows/Start Menu/Programs";
+
+    // The user's own shortcuts, then the ones of all users
+    for var in ["AppData", "ProgramData"] {
+        let path_str = match env::var(var) {
+            Ok(dir) => dir + PROG_DIR,
+            Err(_) => {
+                log::warn!("%{}% isn't set, skipping its Start Menu", var);
+                continue;
+            }
+        };
+        let path = Path::new(&path_str);
+        index_directory(
+            programs,
+            path,
+            path,
+            &SourceType::StartMenu,
+            true,
+            extensions,
+        );
+    }
+}
+
+fn index_path(programs: &mut HashMap<String, Program>, extensions: &[String]) {
+    for path in env::split_paths(&env::var_os("PATH").unwrap_or_default()) {
+        index_directory(programs, &path, &path, &SourceType::Path, false, extensions);
+    }
+}
+
+fn cmd_index() -> Result<(), Error> {
+    let config = load_config();
+
+    // When indexing, use lowercase program path as a key to prevent some duplicates
+    let mut programs: HashMap<String, Program> = HashMap::new();
+    index_start_menu(&mut programs, &config.extensions);
+    log::debug!("Found {} programs in the Start Menu", programs.len());
+    let count = programs.len();
+    index_path(&mut programs, &config.extensions);
+    log::debug!("Found {} programs in PATH", programs.len() - count);
+    for dir in &config.index_dirs {
+        let count = programs.len();
+        let path = Path::new(dir);
+        index_directory(
+            &mut programs,
+            path,
+            path,
+            &SourceType::Folder,
+            true,
+            &config.extensions,
+        );
+        log::debug!("Found {} programs in {}", programs.len() - count, dir);
+    }
+    programs.retain(|_, program| {
+        !config
+            .index_exclude
+            .iter()
+            .any(|pattern| program_matches_glob(program, pattern))
+    });
+
+    // Keys are already lowercase
+    let hidden = load_hidden()?;
+    programs.retain(|key, _| !hidden.contains(key));
+
+    // Carry over when programs were first seen so that new ones can be highlighted
+    if let Ok(index_json_data) = fs::read_to_string(&*INDEX_PATH) {
+        let previous: Vec<Program> = serde_json::from_str(&index_json_data).unwrap_or_default();
+        let first_seen: HashMap<String, u64> = previous
+            .iter()
+            .map(|program| (program.abs_path.to_ascii_lowercase(), program.first_seen))
+            .collect();
+        let time_now = unix_time();
+        for (key, program) in programs.iter_mut() {
+            program.first_seen = *first_seen.get(key).unwrap_or(&time_now);
+        }
+    }
+
+    // Collect into vector since we don't need the dictionary structure anymore
+    let programs: Vec<&Program> = programs.values().collect();
+
+    // Write to file
+    let index_json_data = serde_json::to_string_pretty(&programs).unwrap();
+    try_write_data(&INDEX_PATH, index_json_data)?;
+    println!("Indexed {} programs", programs.len());
+    Ok(())
+}
+
+// The executable a program name like `wlines` is started from, looked up in PATH like Windows does
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains(['/', '\\']) {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .flat_map(|dir| [dir.join(program), dir.join(format!("{}.exe", program))])
+        .find(|path| path.is_file())
+}
+
+// Settings chosen in the first-run setup, written as the start of a new configuration
+#[derive(Serialize)]
+struct SetupConfig {
+    index_dirs: Vec<String>,
+    wlines_args: Vec<String>,
+}
+
+const SETUP_SOURCES: &str = "Only the Start Menu and PATH - or type more folders, separated by ;";
+
+const SETUP_THEMES: [(&str, &[&str]); 3] = [
+    ("Default theme", &[]),
+    (
+        "Dark theme",
+        &[
+            "-bg", "#1e1e1e", "-fg", "#d4d4d4", "-sbg", "#264f78", "-sfg", "#ffffff",
+        ],
+    ),
+    (
+        "Light theme",
+        &[
+            "-bg", "#ffffff", "-fg", "#1e1e1e", "-sbg", "#cce4f7", "-sfg", "#000000",
+        ],
+    ),
+];
+
+// Check that the frontend can be found, write a configuration from a few questions asked in the
+// menu unless there already is one, and build the first index
+fn cmd_setup(wlines_args: &[String]) -> Result<(), Error> {
+    let config = load_config();
+    let program = frontend_program(&config, &*load_frontend(&config)?);
+    match find_program(&program) {
+        Some(path) => log::info!("Using {}", path.display()),
+        None => {
+            return Err(Error::Frontend {
+                program,
+                source: io::Error::new(io::ErrorKind::NotFound, "it isn't in PATH"),
+            })
+        }
+    }
+
+    if config_modified().is_some() {
+        log::info!("Keeping the existing configuration");
+    } else {
+        let sources =
+            prompt(wlines_args, &[String::from(SETUP_SOURCES)]).ok_or(Error::Cancelled)?;
+        let index_dirs = if sources == SETUP_SOURCES {
+            Vec::new()
+        } else {
+            sources
+                .split(';')
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        // Other frontends don't take wlines' color arguments
+        let mut theme_args = Vec::new();
+        if config.frontend == "wlines" {
+            let themes: Vec<String> = SETUP_THEMES
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect();
+            let theme = prompt(wlines_args, &themes).ok_or(Error::Cancelled)?;
+            if let Some((_, args)) = SETUP_THEMES.iter().find(|(name, _)| *name == theme) {
+                theme_args = args.iter().map(|arg| arg.to_string()).collect();
+            }
+        }
+
+        let setup = SetupConfig {
+            index_dirs,
+            wlines_args: theme_args,
+        };
+        if read_only() {
+            log::info!("Not writing {} in read-only mode", *CONFIG_TOML_PATH);
+        } else {
+            if let Some(dir) = Path::new(&*CONFIG_TOML_PATH).parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            try_write_data(&CONFIG_TOML_PATH, toml::to_string(&setup).unwrap())?;
+            log::info!("Wrote {}", *CONFIG_TOML_PATH);
+        }
+    }
+
+    log::info!("Indexing programs...");
+    cmd_index()
+}
+
+fn program_matches(program: &Program, target: &str) -> bool {
+    program.abs_path.eq_ignore_ascii_case(target) || program.title.eq_ignore_ascii_case(target)
+}
+
+// Case-insensitive match where `*` stands for any text and `?` for any single character
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it's currently matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn program_matches_glob(program: &Program, pattern: &str) -> bool {
+    glob_matches(pattern, &program.abs_path) || glob_matches(pattern, &program.title)
+}
+
+fn is_excluded_from_history(config: &Config, program: &Program) -> bool {
+    config
+        .history_exclude
+        .iter()
+        .any(|pattern| program_matches_glob(program, pattern))
+}
+
+// "Microsoft Office/Visual Studio Code.lnk" -> "vsc"
+fn title_acronym(title: &str) -> Option<String> {
+    let name = Path::new(title).file_stem()?.to_str()?;
+    let words: Vec<&str> = name
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_' || c == '.')
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.len() < 2 {
+        return None;
+    }
+    Some(
+        words
+            .iter()
+            .filter_map(|word| word.chars().next())
+            .flat_map(char::to_lowercase)
+            .collect(),
+    )
+}
+
+// "Café Über.lnk" -> "Cafe Uber", for searching without typing accents
+fn fold_diacritics(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.nfd().filter(|&c| !is_combining_mark(c)) {
+        // Letters that don't decompose into a base letter and a combining mark
+        match c {
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'œ' => folded.push_str("oe"),
+            'Œ' => folded.push_str("OE"),
+            'ø' => folded.push('o'),
+            'Ø' => folded.push('O'),
+            'đ' => folded.push('d'),
+            'Đ' => folded.push('D'),
+            'ł' => folded.push('l'),
+            'Ł' => folded.push('L'),
+            'þ' => folded.push_str("th"),
+            'Þ' => folded.push_str("TH"),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+// Aliases, configured keywords, the acronym and the accent-free title, which are all added to
+// the menu line for filtering
+fn program_search_terms(config: &Config, program: &Program) -> Vec<String> {
+    let mut aliases: Vec<String> = config
+        .aliases
+        .iter()
+        .filter(|(_, target)| program_matches(program, target))
+        .map(|(alias, _)| alias.clone())
+        .collect();
+    aliases.sort_unstable();
+
+    let mut search_terms = aliases;
+    for (target, keywords) in &config.keywords {
+        if program_matches(program, target) {
+            search_terms.extend(keywords.iter().cloned());
+        }
+    }
+    if config.acronyms {
+        if let Some(acronym) = title_acronym(&program.title) {
+            search_terms.push(acronym);
+        }
+    }
+    if let Some(name) = Path::new(&program.title)
+        .file_stem()
+        .and_then(|n| n.to_str())
+    {
+        let folded = fold_diacritics(name);
+        if folded != name {
+            search_terms.push(folded);
+        }
+    }
+    let mut seen = HashSet::new();
+    search_terms.retain(|term| seen.insert(term.clone()));
+    search_terms
+}
+
+// Resolve input like `ff -private-window` into the aliased program and its arguments
+fn resolve_alias<'a>(
+    input: &'a str,
+    config: &Config,
+    programs: &'a [Program],
+) -> Option<(&'a Program, &'a str)> {
+    let (alias, arg_string) = match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], &input[i..]),
+        None => (input, ""),
+    };
+    let target = config
+        .aliases
+        .iter()
+        .find(|(a, _)| a.eq_ignore_ascii_case(alias))?
+        .1;
+    let program = programs.iter().find(|p| program_matches(p, target))?;
+    Some((program, arg_string))
+}
+
+// Resolve input like `2` or `2 --safe-mode` into the second menu entry and its arguments
+fn resolve_quick_select<'a>(
+    input: &'a str,
+    quick_select: usize,
+    prog_name_links: &'a [(String, &'a Program)],
+) -> Option<(&'a Program, &'a str)> {
+    let (number, arg_string) = match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], &input[i..]),
+        None => (input, ""),
+    };
+    let number: usize = number.parse().ok()?;
+    if number == 0 || number > quick_select.min(prog_name_links.len()) {
+        return None;
+    }
+    Some((prog_name_links[number - 1].1, arg_string))
+}
+
+// Name of the menu group a program belongs to when grouping by folder
+fn program_folder(program: &Program) -> String {
+    match program.source {
+        SourceType::StartMenu => Path::new(&program.title)
+            .parent()
+            .and_then(|folder| folder.to_str())
+            .filter(|folder| !folder.is_empty())
+            .unwrap_or("Start Menu")
+            .to_string(),
+        SourceType::Path => String::from("PATH"),
+        SourceType::Folder => Path::new(&program.abs_path)
+            .parent()
+            .and_then(|folder| folder.file_name())
+            .map_or_else(String::new, |folder| folder.to_string_lossy().into_owned()),
+    }
+}
+
+fn format_program_display_name(
+    program: &Program,
+    search_terms: &[String],
+    show_source: bool,
+) -> String {
+    let mut name = if show_source {
+        format!("{}] ", program.source.display_name()) + &program.title
+    } else {
+        program.title.clone()
+    };
+    if !search_terms.is_empty() {
+        name += &format!(" [{}]", search_terms.join(" "));
+    }
+    name
+}
+
+// Set from `--wlines-path`, which takes precedence over the configured `wlines_path`
+static WLINES_PATH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+// The subcommand, e.g. `run` or `menu:tools`, for looking up its `mode_args`
+static MODE: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+fn load_frontend(config: &Config) -> Result<Box<dyn frontend::Frontend>, Error> {
+    frontend::by_name(&config.frontend)
+        .ok_or_else(|| Error::Config(format!("Unknown frontend '{}'", config.frontend)))
+}
+
+fn frontend_program(config: &Config, frontend: &dyn frontend::Frontend) -> String {
+    match WLINES_PATH.get() {
+        Some(path) => path.clone(),
+        None if !config.wlines_path.is_empty() => config.wlines_path.clone(),
+        None => frontend.program().to_string(),
+    }
+}
+
+fn frontend_error(source: io::Error) -> Error {
+    let config = load_config();
+    let program = match load_frontend(&config) {
+        Ok(frontend) => frontend_program(&config, &*frontend),
+        Err(_) => config.frontend,
+    };
+    Error::Frontend { program, source }
+}
+
+// The configured frontend with the configured `wlines_args`, then the `mode_args` of the active
+// profile and the subcommand, then the ones from the command line, so that each can override the
+// ones before it
+fn wlines_command(wlines_args: &[String]) -> Result<Command, Error> {
+    let config = load_config();
+    let frontend = load_frontend(&config)?;
+    let mut command = Command::new(frontend_program(&config, &*frontend));
+    command.args(frontend.args()).args(&config.wlines_args);
+    let profile = active_profile();
+    if !profile.is_empty() {
+        if let Some(args) = config.mode_args.get(&format!("profile:{}", profile)) {
+            command.args(args);
+        }
+    }
+    if let Some(args) = MODE.get().and_then(|mode| config.mode_args.get(mode)) {
+        command.args(args);
+    }
+    command.args(wlines_args);
+    Ok(command)
+}
+
+// What was chosen or typed in a menu, or `None` if it was cancelled
+fn menu_selection(output: process::Output) -> Result<Option<String>, Error> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(load_frontend(&load_config())?.selection(output.status.code(), &stdout))
+}
+
+// Show a secondary menu and return the selected or typed line, or `None` if it was cancelled
+fn try_prompt(wlines_args: &[String], lines: &[String]) -> Result<Option<String>, Error> {
+    let mut wlines = wlines_command(wlines_args)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(frontend_error)?;
+
+    {
+        let stdin = wlines.stdin.as_mut().unwrap();
+        stdin
+            .write_all((lines.join("\n") + "\n").as_bytes())
+            .map_err(frontend_error)?;
+    }
+
+    let output = wlines.wait_with_output().map_err(frontend_error)?;
+    Ok(menu_selection(output)?.map(|selection| selection.trim().to_string()))
+}
+
+fn prompt(wlines_args: &[String], lines: &[String]) -> Option<String> {
+    try_prompt(wlines_args, lines).or_exit()
+}
+
+// Like `prompt`, with a label in front of the input that says what is asked for
+fn labeled_prompt(wlines_args: &[String], label: &str, lines: &[String]) -> Option<String> {
+    let mut args = wlines_args.to_vec();
+    args.extend(load_frontend(&load_config()).or_exit().prompt_args(label));
+    prompt(&args, lines)
+}
+
+// Match wlines output like `S] Firefox.lnk: -private-window` against the menu lines. The longest
+// match wins, so that one title followed by a colon can't be mistaken for another title.
+fn match_selection<'a>(
+    input: &'a str,
+    prog_name_links: &[(String, &'a Program)],
+) -> Option<(&'a Program, &'a str)> {
+    prog_name_links
+        .iter()
+        .filter(|(name, _)| {
+            input.starts_with(name.as_str()) && input[name.len()..].starts_with(':')
+        })
+        .max_by_key(|(name, _)| name.len())
+        // + 1 to compensate `:` suffix
+        .map(|(name, program)| (*program, &input[(name.len() + 1)..]))
+}
+
+// Programs with the same title (e.g. the same executable in two PATH directories) get their
+// folder appended, since otherwise only the first one of them could ever be selected
+fn make_display_names_unique(prog_name_links: &mut [(String, &Program)]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (name, _) in prog_name_links.iter() {
+        *counts.entry(name.clone()).or_insert(0) += 1;
+    }
+    for (name, program) in prog_name_links.iter_mut() {
+        if counts[name.as_str()] > 1 {
+            if let Some(folder) = Path::new(&program.abs_path)
+                .parent()
+                .and_then(|f| f.to_str())
+            {
+                *name += &format!(" ({})", folder);
+            }
+        }
+    }
+}
+
+// Order of the menu: pins, then programs new to the index, then by frecency
+struct Ranking<'a> {
+    config: &'a Config,
+    history: &'a HashMap<String, HistoryEntry>,
+    pinned: Vec<String>,
+    new_since: u64,
+    context: Option<LaunchContext>,
+    time_now: u64,
+}
+
+impl<'a> Ranking<'a> {
+    fn new(
+        config: &'a Config,
+        history: &'a HashMap<String, HistoryEntry>,
+        time_now: u64,
+    ) -> Result<Self, Error> {
+        // Pins from the config come first, then the ones added with the `::pin` action
+        let pinned = config
+            .pinned
+            .iter()
+            .chain(load_list(&PINS_PATH)?.iter())
+            .map(|path| path.to_ascii_lowercase())
+            .collect();
+        let context = if config.context_ranking {
+            Some(LaunchContext::now(config.context_weight))
+        } else {
+            None
+        };
+        Ok(Ranking {
+            config,
+            history,
+            pinned,
+            new_since: time_now.saturating_sub(config.new_program_days * 24 * 60 * 60),
+            context,
+            time_now,
+        })
+    }
+
+    fn pin_position(&self, program: &Program) -> Option<usize> {
+        let path = program.abs_path.to_ascii_lowercase();
+        self.pinned.iter().position(|p| *p == path)
+    }
+
+    // Programs recently added to the index that haven't been launched yet
+    fn is_new(&self, program: &Program) -> bool {
+        self.config.new_program_days > 0
+            && program.first_seen > self.new_since
+            && !self.history.contains_key(&program.abs_path)
+    }
+
+    // Frecency, boosted by the launch context, plus the configured score modifiers. `None` for
+    // programs that were never launched and have no modifier.
+    fn score(&self, program: &Program) -> Option<f64> {
+        let modifier: f64 = self
+            .config
+            .score_modifiers
+            .iter()
+            .filter(|(pattern, _)| program_matches_glob(program, pattern))
+            .map(|(_, modifier)| modifier)
+            .sum();
+        match self.history.get(&program.abs_path) {
+            Some(entry) => {
+                let boost = self
+                    .context
+                    .as_ref()
+                    .map_or(1.0, |context| context.boost(entry));
+                Some(frecency(entry, self.time_now) * boost + modifier)
+            }
+            None if modifier != 0.0 => Some(modifier),
+            None => None,
+        }
+    }
+
+    // Programs that a negative score modifier puts after all the others
+    fn is_penalised(&self, program: &Program) -> bool {
+        self.score(program).is_some_and(|score| score < 0.0)
+    }
+
+    fn is_ranked(&self, program: &Program) -> bool {
+        self.pin_position(program).is_some()
+            || self.is_new(program)
+            || self.score(program).is_some_and(|score| score >= 0.0)
+    }
+
+    fn sort(&self, programs: &mut [Program]) {
+        // Matching the modifier patterns is too slow to do for every comparison
+        let scores: HashMap<String, Option<f64>> = programs
+            .iter()
+            .map(|program| (program.abs_path.clone(), self.score(program)))
+            .collect();
+        programs.sort_by(|a, b| match (self.pin_position(a), self.pin_position(b)) {
+            (Some(a_pos), Some(b_pos)) => a_pos.cmp(&b_pos),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => self.is_new(b).cmp(&self.is_new(a)).then_with(|| {
+                compare_scores(
+                    a,
+                    b,
+                    scores[&a.abs_path],
+                    scores[&b.abs_path],
+                    &self.config.tie_breakers,
+                )
+            }),
+        });
+    }
+}
+
+// What a selection is matched against
+struct Menu<'a> {
+    config: &'a Config,
+    programs: &'a [Program],
+    prog_name_links: Vec<(String, &'a Program)>,
+    group_headers: HashSet<String>,
+    wlines_args: &'a [String],
+    dry_run: bool,
+    open_file: Option<&'a str>,
+    history_path: &'a str,
+    record_history: bool,
+    // Set when a chosen program couldn't be started, for the exit code
+    launch_failed: Cell<bool>,
+}
+
+// Open a data file, creating it with `contents` if it doesn't exist yet
+fn open_data_file(menu: &Menu, path: &str, contents: &str) -> Result<(), Error> {
+    if !Path::new(path).exists() {
+        try_write_data(path, contents)?;
+    }
+    let options = LaunchOptions {
+        dry_run: menu.dry_run,
+        ..LaunchOptions::default()
+    };
+    if let Err(err) = launch::launch(path, &[], &options) {
+        report_error(
+            menu.wlines_args,
+            &format!("Couldn't open \"{}\": {}", path, err),
+        );
+    }
+    Ok(())
+}
+
+// Make another of the configured `profiles` active, which takes effect from the next run
+fn switch_profile(menu: &Menu) -> Result<(), Error> {
+    let current = active_profile();
+    let profiles: Vec<String> = Some(String::from("default"))
+        .into_iter()
+        .chain(menu.config.profiles.iter().cloned())
+        .map(|profile| {
+            let active = profile == current || (current.is_empty() && profile == "default");
+            if active {
+                format!("* {}", profile)
+            } else {
+                profile
+            }
+        })
+        .collect();
+    let choice = match try_prompt(menu.wlines_args, &profiles)? {
+        Some(choice) => choice,
+        None => return Ok(()),
+    };
+    let profile = choice.trim_start_matches("* ");
+    let profile = if profile == "default" { "" } else { profile };
+    if !profile.is_empty() && !menu.config.profiles.iter().any(|p| p == profile) {
+        log::info!("Unknown profile '{}'", profile);
+        return Ok(());
+    }
+    try_write_data(&PROFILE_PATH, profile)?;
+    log::info!("Switched to profile '{}'", choice.trim_start_matches("* "));
+    Ok(())
+}
+
+// Run one of wlines_run's own actions for input like `>reindex`, picking it in a menu for `>`
+// alone. Returns whether the input was meant for the palette.
+fn run_palette(
+    menu: &Menu,
+    input_string: &str,
+    history: &mut HashMap<String, HistoryEntry>,
+) -> Result<bool, Error> {
+    let typed = match input_string.strip_prefix('>') {
+        Some(typed) => typed.trim(),
+        None => return Ok(false),
+    };
+    let action = if typed.is_empty() {
+        let labels: Vec<String> = PALETTE.iter().map(|(label, _)| label.to_string()).collect();
+        let choice = match try_prompt(menu.wlines_args, &labels)? {
+            Some(choice) => choice,
+            None => return Ok(true),
+        };
+        PALETTE
+            .iter()
+            .find(|(label, _)| *label == choice)
+            .map(|(_, action)| *action)
+    } else {
+        PALETTE
+            .iter()
+            .find(|(label, action)| {
+                label.eq_ignore_ascii_case(typed) || action.eq_ignore_ascii_case(typed)
+            })
+            .map(|(_, action)| *action)
+    };
+
+    match action {
+        Some("reindex") => cmd_index()?,
+        Some("config") => open_data_file(menu, editable_config_path()?, "")?,
+        Some("history") => open_data_file(menu, menu.history_path, "{}")?,
+        Some("profile") => switch_profile(menu)?,
+        Some("stats") => {
+            let merged_history = merge_synced_histories(menu.history_path, history);
+            try_prompt(menu.wlines_args, &stats(&merged_history, unix_time(), 10))?;
+        }
+        // The index may be out of date too, so every entry is looked up
+        Some("prune") => {
+            let pruned = drop_missing_programs(history, &[]);
+            log::info!("Pruned {} entries", pruned);
+        }
+        _ => report_error(
+            menu.wlines_args,
+            &format!("Unknown action '{}'", input_string),
+        ),
+    }
+    Ok(true)
+}
+
+// Show one of the configured `menus` and run the chosen command line
+fn run_custom_menu(
+    config: &Config,
+    name: &str,
+    wlines_args: &[String],
+    dry_run: bool,
+) -> Result<(), Error> {
+    let entries = match config.menus.get(name) {
+        Some(entries) => entries,
+        None => {
+            report_error(wlines_args, &format!("No menu named '{}'", name));
+            return Ok(());
+        }
+    };
+    let mut titles: Vec<String> = entries.keys().cloned().collect();
+    titles.sort_by(|a, b| collate(a, b));
+    let choice = match try_prompt(wlines_args, &titles)? {
+        Some(choice) => choice,
+        None => return Ok(()),
+    };
+    let command_line = match entries.get(&choice) {
+        Some(command_line) => command_line,
+        None => {
+            log::info!("Unknown entry '{}'", choice);
+            return Ok(());
+        }
+    };
+    log::info!("Running \"{}\"", command_line);
+    let options = LaunchOptions {
+        dry_run,
+        ..LaunchOptions::default()
+    };
+    if let Err(err) = launch::run_command_line(command_line, &options) {
+        report_error(
+            wlines_args,
+            &format!("Couldn't run \"{}\": {}", command_line, err),
+        );
+    }
+    Ok(())
+}
+
+// Open a configured menu if its name was typed, returning whether it was
+fn run_custom_menu_keyword(menu: &Menu, input_string: &str) -> Result<bool, Error> {
+    let name = input_string.trim();
+    if !menu.config.menus.contains_key(name) {
+        return Ok(false);
+    }
+    run_custom_menu(menu.config, name, menu.wlines_args, menu.dry_run)?;
+    Ok(true)
+}
+
+fn cmd_custom_menu(name: &str, dry_run: bool, wlines_args: &[String]) -> Result<(), Error> {
+    let config = load_config();
+    run_custom_menu(&config, name, wlines_args, dry_run)
+}
+
+// Evaluate input like `=2^10`, `12 * 4` or `=10 mi in km` and offer to copy the result,
+// returning whether the input was meant for the calculator
+fn run_calculator(menu: &Menu, input_string: &str) -> Result<bool, Error> {
+    let expression = match input_string.strip_prefix('=') {
+        Some(expression) => expression.trim(),
+        None if calc::looks_like_expression(input_string) => input_string,
+        None => return Ok(false),
+    };
+    let result = match units::convert(expression)
+        .or_else(|| calc::evaluate(expression).map(calc::format_number))
+    {
+        Some(result) => result,
+        None => {
+            report_error(
+                menu.wlines_args,
+                &format!("Couldn't evaluate '{}'", expression),
+            );
+            return Ok(true);
+        }
+    };
+    log::info!("{} = {}", expression, result);
+    let choice = try_prompt(menu.wlines_args, std::slice::from_ref(&result))?;
+    if choice.as_deref() == Some(result.as_str()) {
+        if let Err(err) = clipboard::set_text(&result) {
+            report_error(menu.wlines_args, &format!("Couldn't copy result: {}", err));
+        }
+    }
+    Ok(true)
+}
+
+// Percent-encode a query for use in a URL
+fn url_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded += &format!("%{:02X}", byte),
+        }
+    }
+    encoded
+}
+
+// Search files with Everything's `es.exe` for input like `'report.pdf` or `f report pdf`,
+// streaming its results into wlines and opening the chosen one. Returns whether the input was a
+// file search.
+fn run_file_search(menu: &Menu, input_string: &str) -> Result<bool, Error> {
+    let query = match input_string
+        .strip_prefix('\'')
+        .or_else(|| input_string.strip_prefix("f "))
+    {
+        Some(query) if !query.trim().is_empty() => query.trim(),
+        _ => return Ok(false),
+    };
+
+    let mut everything = match Command::new(&menu.config.everything_command)
+        .args(["-cp", "65001", "-n"])
+        .arg(menu.config.file_search_results.to_string())
+        .args(query.split_whitespace())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(everything) => everything,
+        Err(err) => {
+            report_error(
+                menu.wlines_args,
+                &format!("Couldn't start {}: {}", menu.config.everything_command, err),
+            );
+            return Ok(true);
+        }
+    };
+    let output = wlines_command(menu.wlines_args)?
+        .stdin(everything.stdout.take().unwrap())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|wlines| wlines.wait_with_output())
+        .map_err(frontend_error);
+    let _ = everything.kill();
+    let _ = everything.wait();
+    let path = match menu_selection(output?)? {
+        Some(path) => path.trim().to_string(),
+        None => {
+            log::info!("Exited");
+            return Ok(true);
+        }
+    };
+    if path.is_empty() {
+        return Ok(true);
+    }
+    log::info!("Opening \"{}\"", path);
+    let options = LaunchOptions {
+        dry_run: menu.dry_run,
+        ..LaunchOptions::default()
+    };
+    match launch::launch(&path, &[], &options) {
+        Ok(()) if !menu.dry_run && menu.record_history && Path::new(&path).is_dir() => {
+            record_dir(&path, unix_time())?
+        }
+        Ok(()) => {}
+        Err(err) => report_error(
+            menu.wlines_args,
+            &format!("Couldn't open \"{}\": {}", path, err),
+        ),
+    }
+    Ok(true)
+}
+
+// URL to open for input like `https://example.com`, `www.example.com` or `github.com/foo`
+fn input_url(input: &str) -> Option<String> {
+    if input.contains(char::is_whitespace) {
+        return None;
+    }
+    let lowercase = input.to_ascii_lowercase();
+    if lowercase.starts_with("http://") || lowercase.starts_with("https://") {
+        return Some(input.to_string());
+    }
+
+    // A domain of at least two labels, ending in a top-level domain that isn't a file extension
+    let host = input.split(['/', '?', '#']).next()?;
+    let host = host.split(':').next()?;
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels.last()?.to_ascii_lowercase();
+    let is_domain = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && !["exe", "lnk", "bat", "cmd", "msc", "txt"].contains(&tld.as_str());
+    if is_domain {
+        Some(String::from("https://") + input)
+    } else {
+        None
+    }
+}
+
+// Fill the `{}` of a search engine's URL template with the query
+fn search_url(template: &str, query: &str) -> String {
+    template.replace("{}", &url_encode(query))
+}
+
+fn open_url(menu: &Menu, url: &str) {
+    log::info!("Opening \"{}\"", url);
+    let options = LaunchOptions {
+        dry_run: menu.dry_run,
+        ..LaunchOptions::default()
+    };
+    if let Err(err) = launch::launch(url, &[], &options) {
+        report_error(
+            menu.wlines_args,
+            &format!("Couldn't open \"{}\": {}", url, err),
+        );
+    }
+}
+
+// Search the web for input like `g rust traits` if it starts with a search engine's keyword,
+// returning whether it did
+fn run_web_search(menu: &Menu, input_string: &str) -> bool {
+    let (keyword, query) = match input_string.split_once(char::is_whitespace) {
+        Some((keyword, query)) if !query.trim().is_empty() => (keyword, query.trim()),
+        _ => return false,
+    };
+    match menu
+        .config
+        .search_engines
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(keyword))
+    {
+        Some((_, template)) => {
+            open_url(menu, &search_url(template, query));
+            true
+        }
+        None => false,
+    }
+}
+
+// Search winget for input like `wg firefox`, returning whether it did
+fn run_winget_search(menu: &Menu, input_string: &str) -> Result<bool, Error> {
+    match input_string.strip_prefix("wg ") {
+        Some(query) if !query.trim().is_empty() => {
+            winget_install(query.trim(), menu.wlines_args, menu.dry_run)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+enum Fallback {
+    Open(String),
+    Run(String),
+}
+
+// Offer what can be done with input that isn't an entry: opening it if it's a URL, searching the
+// web with the default engine or running it as a command in the configured shell, like the Run
+// dialog
+fn run_fallback(menu: &Menu, input_string: &str) -> Result<(), Error> {
+    let config = menu.config;
+    let input = input_string.trim();
+    let mut offers = Vec::new();
+    if !input.is_empty() {
+        if let Some(url) = input_url(input) {
+            offers.push((format!("Open in browser: {}", url), Fallback::Open(url)));
+        }
+        if !config.default_search_engine.is_empty() {
+            offers.push((
+                format!("Search the web: {}", input),
+                Fallback::Open(search_url(&config.default_search_engine, input)),
+            ));
+        }
+        if !config.fallback_shell.is_empty() {
+            offers.push((
+                format!("Run command: {}", input),
+                Fallback::Run(format!("{} {}", config.fallback_shell, input)),
+            ));
+        }
+    }
+    if offers.is_empty() {
+        log::info!("Unknown choice '{}'", input_string);
+        return Ok(());
+    }
+
+    let labels: Vec<String> = offers.iter().map(|(label, _)| label.clone()).collect();
+    let choice = match try_prompt(menu.wlines_args, &labels)? {
+        Some(choice) => choice,
+        None => {
+            log::info!("Exited");
+            return Ok(());
+        }
+    };
+    match offers.iter().find(|(label, _)| *label == choice) {
+        Some((_, Fallback::Open(url))) => open_url(menu, url),
+        Some((_, Fallback::Run(command_line))) => {
+            log::info!("Running \"{}\"", command_line);
+            let options = LaunchOptions {
+                dry_run: menu.dry_run,
+                ..LaunchOptions::default()
+            };
+            if let Err(err) = launch::run_command_line(command_line, &options) {
+                report_error(
+                    menu.wlines_args,
+                    &format!("Couldn't run \"{}\": {}", command_line, err),
+                );
+            }
+        }
+        None => log::info!("Unknown choice '{}'", choice),
+    }
+    Ok(())
+}
+
+// Launch a program with its configured working directory, environment and window state,
+// starting console programs in the configured terminal
+fn start_program(
+    config: &Config,
+    program: &Program,
+    prog_args: &[String],
+    verb: Option<&str>,
+    dry_run: bool,
+) -> io::Result<()> {
+    log::info!("Starting \"{}\"", program.abs_path);
+    let working_dir = program_working_dir(config, program);
+    let options = LaunchOptions {
+        verb,
+        working_dir: working_dir.as_deref(),
+        environment: config
+            .environment
+            .iter()
+            .find(|(target, _)| program_matches(program, target))
+            .map(|(_, environment)| environment),
+        window_state: config
+            .window_states
+            .iter()
+            .find(|(target, _)| program_matches(program, target))
+            .map_or(WindowState::Normal, |(_, state)| *state),
+        dry_run,
+    };
+    if options.verb.is_none() && runs_in_terminal(config, program) {
+        launch::launch_in_terminal(
+            &config.console_terminal,
+            &program.abs_path,
+            prog_args,
+            &options,
+        )
+    } else {
+        launch::launch(&program.abs_path, prog_args, &options)
+    }
+}
+
+fn run_post_launch_hook(config: &Config, program: &Program, arg_string: &str) {
+    if config.post_launch_hook.is_empty() {
+        return;
+    }
+    let environment = [
+        ("WLINES_RUN_TITLE", program.title.as_str()),
+        ("WLINES_RUN_PATH", program.abs_path.as_str()),
+        ("WLINES_RUN_ARGS", arg_string),
+    ];
+    if let Err(err) = launch::run_hook(&config.post_launch_hook, &environment) {
+        log::error!("Couldn't run post-launch hook: {}", err);
+    }
+}
+
+// Run a single line of wlines output, recording launches in the history
+fn run_selection(
+    menu: &Menu,
+    input_string: &str,
+    history: &mut HashMap<String, HistoryEntry>,
+    time_now: u64,
+) -> Result<(), Error> {
+    let Menu {
+        config,
+        programs,
+        prog_name_links,
+        group_headers,
+        wlines_args,
+        ..
+    } = menu;
+
+    // Match selection
+    if group_headers.contains(input_string) {
+        log::debug!("Selected a group header");
+        return Ok(());
+    }
+    let (chosen_prog, arg_string) = if let Some(x) = match_selection(input_string, prog_name_links)
+    {
+        x
+    } else if let Some(x) = resolve_quick_select(input_string, config.quick_select, prog_name_links)
+    {
+        x
+    } else if let Some(x) = resolve_alias(input_string, config, programs) {
+        x
+    } else {
+        if !run_palette(menu, input_string, history)?
+            && !run_custom_menu_keyword(menu, input_string)?
+            && !run_calculator(menu, input_string)?
+            && !run_file_search(menu, input_string)?
+            && !run_winget_search(menu, input_string)?
+            && !run_web_search(menu, input_string)
+        {
+            run_fallback(menu, input_string)?;
+        }
+        return Ok(());
+    };
+
+    // Extract input arguments
+    let mut arg_string = arg_string.trim();
+
+    // Actions are typed in place of arguments, e.g. `S] Firefox: ::pin`
+    let mut action = arg_string.strip_prefix("::").map(str::trim);
+
+    // `::` alone or the configured suffix opens a menu of actions
+    let mut open_action_menu = action == Some("");
+    if !config.action_menu_suffix.is_empty() {
+        if let Some(rest) = arg_string.strip_suffix(config.action_menu_suffix.as_str()) {
+            arg_string = rest.trim_end();
+            open_action_menu = true;
+        }
+    }
+    if open_action_menu {
+        let labels: Vec<String> = ACTION_MENU
+            .iter()
+            .map(|(label, _)| label.to_string())
+            .collect();
+        let choice = match try_prompt(wlines_args, &labels)? {
+            Some(choice) => choice,
+            None => {
+                log::info!("Exited");
+                return Ok(());
+            }
+        };
+        action = match ACTION_MENU.iter().find(|(label, _)| *label == choice) {
+            Some((_, action)) => Some(action),
+            None => {
+                log::info!("Unknown action '{}'", choice);
+                return Ok(());
+            }
+        };
+    }
+
+    let chosen_args;
+    let (mut elevate, mut as_user) = (false, false);
+    let mut verb = None;
+    let mut pick_file = false;
+    match action {
+        None | Some("run") => {}
+        Some("edit") | Some("print") => verb = action,
+        // Any other shell verb, e.g. `::verb openas`
+        Some(action) if action.starts_with("verb ") => verb = Some(action[5..].trim()),
+        Some("admin") => elevate = true,
+        Some("user") => as_user = true,
+        Some("file") => pick_file = true,
+        Some("args") => {
+            let previous = history
+                .get(&chosen_prog.abs_path)
+                .map_or(&[][..], |entry| &entry.args[..]);
+            chosen_args = match try_prompt(wlines_args, previous)? {
+                Some(args) => args,
+                None => return Ok(()),
+            };
+            arg_string = &chosen_args;
+        }
+        Some("preset") => {
+            chosen_args = match choose_preset(wlines_args, config, chosen_prog)? {
+                Some(args) => args,
+                None => return Ok(()),
+            };
+            arg_string = &chosen_args;
+        }
+        Some(action) => {
+            return run_action(wlines_args, action, chosen_prog);
+        }
+    }
+
+    // A leading `!` runs the program as administrator, e.g. `P] cmd.exe: !/k whoami`
+    if let Some(rest) = arg_string.strip_prefix('!') {
+        elevate = true;
+        arg_string = rest;
+    }
+    elevate = elevate
+        || config
+            .run_as_admin
+            .iter()
+            .any(|target| program_matches(chosen_prog, target));
+
+    // A leading `@` runs the program as another user, picked in a second menu
+    if let Some(rest) = arg_string.strip_prefix('@') {
+        as_user = true;
+        arg_string = rest;
+    }
+
+    // A leading `>` picks a file to open with the program, e.g. `S] Notepad++.lnk: >`
+    if let Some(rest) = arg_string.strip_prefix('>') {
+        pick_file = true;
+        arg_string = rest;
+    }
+
+    let mut prog_args = launch::split_args(arg_string);
+
+    let chosen_file = if let Some(file) = menu.open_file {
+        prog_args.push(String::from(file));
+        Some(String::from(file))
+    } else if pick_file {
+        match choose_file(wlines_args, time_now)? {
+            Some(file) => {
+                prog_args.push(file.clone());
+                Some(file)
+            }
+            None => {
+                log::info!("No file chosen");
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
+
+    if config
+        .confirm
+        .iter()
+        .any(|pattern| program_matches_glob(chosen_prog, pattern))
+    {
+        let confirmation = format!("Yes, start {}", chosen_prog.title);
+        let choices = [confirmation.clone(), String::from("No")];
+        if try_prompt(wlines_args, &choices)?.as_deref() != Some(confirmation.as_str()) {
+            log::info!("Not confirmed");
+            return Ok(());
+        }
+    }
+
+    if as_user {
+        let user = match try_prompt(wlines_args, &load_list(&USERS_PATH)?)? {
+            Some(user) if !user.is_empty() => user,
+            _ => {
+                log::info!("No user chosen");
+                return Ok(());
+            }
+        };
+        add_to_list(&USERS_PATH, &user)?;
+        log::info!("Starting \"{}\" as {}", chosen_prog.abs_path, user);
+        let options = LaunchOptions {
+            dry_run: menu.dry_run,
+            ..LaunchOptions::default()
+        };
+        if let Err(err) = launch::launch_as_user(&chosen_prog.abs_path, &prog_args, &user, &options)
+        {
+            report_error(
+                wlines_args,
+                &format!(
+                    "Couldn't start \"{}\" as {}: {}",
+                    chosen_prog.abs_path, user, err
+                ),
+            );
+        }
+        return Ok(());
+    }
+
+    // Programs whose manifest requires administrator would otherwise fail to start
+    if !elevate && verb.is_none() && pe::requires_administrator(&program_target(chosen_prog)) {
+        if config.confirm_auto_elevate {
+            let choices = [String::from("Run as administrator"), String::from("Cancel")];
+            if try_prompt(wlines_args, &choices)?.as_deref() != Some("Run as administrator") {
+                log::info!("Not elevated");
+                return Ok(());
+            }
+        }
+        elevate = true;
+    }
+
+    let verb = if elevate { Some("runas") } else { verb };
+    if let Err(err) = start_program(config, chosen_prog, &prog_args, verb, menu.dry_run) {
+        // Only programs that were launched before are ranked lower, others have no rank to lose
+        if !menu.dry_run && menu.record_history {
+            if let Some(entry) = history.get_mut(&chosen_prog.abs_path) {
+                entry.failures += 1;
+            }
+        }
+        menu.launch_failed.set(true);
+        report_error(
+            wlines_args,
+            &format!("Couldn't start \"{}\": {}", chosen_prog.abs_path, err),
+        );
+        return Ok(());
+    }
+
+    if menu.dry_run {
+        return Ok(());
+    }
+
+    run_post_launch_hook(config, chosen_prog, arg_string.trim());
+
+    if !menu.record_history || is_excluded_from_history(config, chosen_prog) {
+        return Ok(());
+    }
+
+    // Save to history
+    let entry = history.entry(chosen_prog.abs_path.to_string()).or_default();
+    entry.record_launch(time_now);
+    entry.remember_args(arg_string.trim());
+    if let Some(file) = chosen_file {
+        record_file(&file, time_now)?;
+    }
+    Ok(())
+}
+
+// Options of the `run` command, which are taken out of the arguments passed to wlines
+#[derive(Default)]
+struct RunFlags {
+    dry_run: bool,
+    // Set by `open <file>` to open the file with the chosen program
+    open_file: Option<String>,
+    /// History file used instead of the active profile's
+    history: Option<String>,
+    /// Don't record launches
+    no_history: bool,
+    /// Rebuild the index before showing it
+    reindex: bool,
+}
+
+fn split_run_args(args: cli::RunArgs) -> (RunFlags, Vec<String>) {
+    let flags = RunFlags {
+        dry_run: args.dry_run,
+        open_file: None,
+        history: args.history,
+        no_history: args.no_history,
+        reindex: args.reindex,
+    };
+    (flags, args.wlines.wlines_args)
+}
+
+// Open a file with the program chosen from the menu, e.g. from a file manager's "Open with"
+fn cmd_open(file: &str, mut flags: RunFlags, wlines_args: Vec<String>) -> Result<(), Error> {
+    let path = Path::new(file);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+    flags.open_file = Some(path.to_string_lossy().into_owned());
+    cmd_run(flags, wlines_args)
+}
+
+fn cmd_run(flags: RunFlags, wlines_args: Vec<String>) -> Result<(), Error> {
+    if !Path::new(&*INDEX_PATH).exists() && config_modified().is_none() {
+        cmd_setup(&wlines_args)?;
+    }
+    if !show_run_menu(&flags, &wlines_args, &mut RunCache::default())? {
+        return Err(Error::LaunchFailed);
+    }
+    Ok(())
+}
+
+// The programs in the order of the menu, with the lines that wlines shows for them
+struct RunMenu {
+    programs: Vec<Program>,
+    names: Vec<String>,
+    group_headers: HashSet<String>,
+    text: String,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// A value read from files, along with what it was read from and when they were modified
+struct Cached<K, T> {
+    key: K,
+    value: T,
+}
+
+// The index and the list of hidden programs
+type IndexKey = (Option<SystemTime>, Option<SystemTime>);
+type HistoryKey = (String, Option<SystemTime>);
+// The configuration also changes the menu
+type RunMenuKey = (IndexKey, HistoryKey, Option<SystemTime>);
+
+// What the daemon keeps between menus. Each part is read again only when its files changed.
+#[derive(Default)]
+struct RunCache {
+    index: Option<Cached<IndexKey, Vec<Program>>>,
+    history: Option<Cached<HistoryKey, HashMap<String, HistoryEntry>>>,
+    menu: Option<Cached<RunMenuKey, RunMenu>>,
+}
+
+impl RunCache {
+    fn prepare(&mut self, config: &Config, history_path: &str, time_now: u64) -> Result<(), Error> {
+        let index_key = (modified(&INDEX_PATH), modified(&BLACKLIST_PATH));
+        if self.index.as_ref().map(|index| &index.key) != Some(&index_key) {
+            let programs = try_load_index()?;
+            log::debug!("Loaded {} indexed programs", programs.len());
+            self.index = Some(Cached {
+                key: index_key,
+                value: programs,
+            });
+        }
+        let history_key = (history_path.to_string(), modified(history_path));
+        if self.history.as_ref().map(|history| &history.key) != Some(&history_key) {
+            self.history = Some(Cached {
+                key: history_key.clone(),
+                value: try_load_history(history_path)?,
+            });
+        }
+        let menu_key = (index_key, history_key, config_modified());
+        if self.menu.as_ref().map(|menu| &menu.key) != Some(&menu_key) {
+            let programs = self.index.as_ref().unwrap().value.clone();
+            let mut history = self.history.as_ref().unwrap().value.clone();
+            drop_missing_programs(&mut history, &programs);
+            self.menu = Some(Cached {
+                key: menu_key,
+                value: build_run_menu(config, programs, &history, history_path, time_now)?,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn build_run_menu(
+    config: &Config,
+    mut programs: Vec<Program>,
+    history: &HashMap<String, HistoryEntry>,
+    history_path: &str,
+    time_now: u64,
+) -> Result<RunMenu, Error> {
+    let merged_history = merge_synced_histories(history_path, history);
+    let ranking = Ranking::new(config, &merged_history, time_now)?;
+    ranking.sort(&mut programs);
+    if config.launched_today_first {
+        // Most recent first, so switching back and forth between a few programs is one keystroke
+        let midnight = start_of_day();
+        programs.sort_by_key(|program| {
+            std::cmp::Reverse(
+                history
+                    .get(&program.abs_path)
+                    .map(|entry| entry.access)
+                    .filter(|access| *access >= midnight),
+            )
+        });
+    }
+
+    // Programs that aren't ranked in any way are grouped after the ranked ones, and before the
+    // penalised ones, which stay last
+    let ranked_count = programs
+        .iter()
+        .take_while(|program| ranking.is_ranked(program))
+        .count();
+    let grouped_end = ranked_count
+        + programs[ranked_count..]
+            .iter()
+            .take_while(|program| !ranking.is_penalised(program))
+            .count();
+    if config.group_by_folder {
+        programs[ranked_count..grouped_end].sort_by(|a, b| {
+            collate(&program_folder(a), &program_folder(b))
+                .then_with(|| collate(&a.title, &b.title))
+        });
+    }
+
+    // Create display names for each program that link back to each program
+    let mut prog_name_links: Vec<(String, &Program)> = programs
+        .iter()
+        .enumerate()
+        .map(|(i, program)| {
+            let search_terms = program_search_terms(config, program);
+            let mut name = format_program_display_name(program, &search_terms, config.show_source);
+            if ranking.is_new(program) {
+                name = String::from("* ") + &name;
+            }
+            if config.mark_failed
+                && merged_history
+                    .get(&program.abs_path)
+                    .is_some_and(|entry| entry.failures > 0)
+            {
+                name = String::from("! ") + &name;
+            }
+            if i < config.quick_select {
+                name = format!("{}) ", i + 1) + &name;
+            }
+            if config.show_run_count {
+                if let Some(entry) = merged_history.get(&program.abs_path) {
+                    name += &format!(" ({})", entry.rank);
+                }
+            }
+            (name, program)
+        })
+        .collect();
+    make_display_names_unique(&mut prog_name_links);
+
+    let mut group_headers: HashSet<String> = HashSet::new();
+    let mut text = String::new();
+    let mut current_folder = None;
+    for (i, prog_name_link) in prog_name_links.iter().enumerate() {
+        if config.group_by_folder && (ranked_count..grouped_end).contains(&i) {
+            let folder = program_folder(prog_name_link.1);
+            if current_folder.as_ref() != Some(&folder) {
+                let header = format!("== {} ==", folder);
+                text += &header;
+                text += "\n";
+                group_headers.insert(header);
+                current_folder = Some(folder);
+            }
+        }
+        text += &prog_name_link.0;
+        text += ": \n";
+    }
+
+    let names = prog_name_links.into_iter().map(|(name, _)| name).collect();
+    Ok(RunMenu {
+        programs,
+        names,
+        group_headers,
+        text,
+    })
+}
+
+// Show the menu of programs and launch the chosen ones, reading only what changed since the
+// cache was filled. `Ok(false)` if a chosen program couldn't be started.
+fn show_run_menu(
+    flags: &RunFlags,
+    wlines_args: &[String],
+    cache: &mut RunCache,
+) -> Result<bool, Error> {
+    // Held until the history is saved, so that two menus can't overwrite each other's launches
+    let guard = match instance::acquire() {
+        Some(guard) => guard,
+        None => {
+            log::debug!("A menu is already open");
+            return Err(Error::Cancelled);
+        }
+    };
+
+    // Start wlines right away
+    let mut wlines = wlines_command(wlines_args)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(frontend_error)?;
+    guard.set_frontend(wlines.id());
+
+    // wlines is already starting meanwhile, so its window shows up as soon as it would otherwise
+    if flags.reindex {
+        cmd_index()?;
+    }
+    let config = load_config();
+    let time_now = unix_time();
+    let history_path = flags.history.clone().unwrap_or_else(default_history_path);
+    cache.prepare(&config, &history_path, time_now)?;
+    let original_history = &cache.history.as_ref().unwrap().value;
+    let mut history = original_history.clone();
+    let run_menu = &cache.menu.as_ref().unwrap().value;
+
+    // Send to wlines
+    {
+        let stdin = wlines.stdin.as_mut().unwrap();
+        stdin
+            .write_all(run_menu.text.as_bytes())
+            .map_err(frontend_error)?;
+    }
+
+    // Wait for output
+    let output = wlines.wait_with_output().map_err(frontend_error)?;
+    let output = menu_selection(output)?.ok_or(Error::Cancelled)?;
+
+    // Several entries can be selected in one go with ctrl+enter, one per line
+    let menu = Menu {
+        config: &config,
+        programs: &run_menu.programs,
+        prog_name_links: run_menu
+            .names
+            .iter()
+            .cloned()
+            .zip(run_menu.programs.iter())
+            .collect(),
+        group_headers: run_menu.group_headers.clone(),
+        wlines_args,
+        dry_run: flags.dry_run,
+        open_file: flags.open_file.as_deref(),
+        history_path: &history_path,
+        record_history: config.record_history && !flags.no_history,
+        launch_failed: Cell::new(false),
+    };
+    let dropped = drop_missing_programs(&mut history, &run_menu.programs);
+    if dropped > 0 {
+        log::debug!("Dropped {} missing programs from the history", dropped);
+    }
+    for input_string in output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        run_selection(&menu, input_string, &mut history, time_now)?;
+    }
+    let launched = !menu.launch_failed.get();
+    if history != *original_history {
+        let _lock = try_lock_history(&history_path)?;
+        let mut current_history = try_load_history(&history_path)?;
+        apply_history_changes(&mut current_history, original_history, &history);
+        tidy_history(&config, &mut current_history, time_now);
+        try_save_history(&history_path, &current_history, config.encrypt_history)?;
+        cache.history = None;
+    }
+    Ok(launched)
+}
+
+// Keep the index, history and menu in memory, and show the menu whenever `wlines_run show` asks
+// for it, which is quicker than starting `wlines_run run` each time
+fn cmd_daemon() -> Result<(), Error> {
+    watch_config();
+    let mut cache = RunCache::default();
+    prepare_menu(&mut cache);
+    start_tray();
+    log::info!("Waiting for `wlines_run show`");
+    let result = pipe::serve(|message| {
+        let wlines_args: Vec<String> = serde_json::from_str(&message).unwrap_or_default();
+        match show_run_menu(&RunFlags::default(), &wlines_args, &mut cache) {
+            Ok(_) | Err(Error::Cancelled) => {}
+            Err(err) => log::error!("{}", err),
+        }
+        // Get the next menu ready while waiting
+        prepare_menu(&mut cache);
+    });
+    result.map_err(|source| Error::Io {
+        action: "wait for `wlines_run show`",
+        source,
+    })
+}
+
+// Errors are shown once the menu is asked for, since everything is read again then
+fn prepare_menu(cache: &mut RunCache) {
+    if let Err(err) = cache.prepare(&load_config(), &default_history_path(), unix_time()) {
+        log::error!("{}", err);
+    }
+}
+
+// Names of the configured `hotkeys`, sorted so that changes to them can be noticed
+fn hotkey_names(config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = config.hotkeys.keys().cloned().collect();
+    names.sort_unstable();
+    names
+}
+
+fn parse_hotkeys(names: &[String]) -> Vec<(String, hotkeys::Hotkey)> {
+    names
+        .iter()
+        .filter_map(|name| match hotkeys::parse(name) {
+            Some(hotkey) => Some((name.clone(), hotkey)),
+            None => {
+                log::warn!("Unknown hotkey '{}'", name);
+                None
+            }
+        })
+        .collect()
+}
+
+// Show the tray icon and listen for the configured `hotkeys` on a thread of their own, which ends
+// the daemon when "Quit" is chosen. The hotkeys are registered again when they're changed, and
+// look up what they run when pressed.
+fn start_tray() {
+    let mut names = hotkey_names(&load_config());
+    let keys = parse_hotkeys(&names);
+    std::thread::spawn(move || {
+        let on_hotkey = |name: &str| {
+            if let Some(args) = load_config().hotkeys.get(name) {
+                run_hotkey(args);
+            }
+        };
+        let reload_hotkeys = || {
+            let changed = hotkey_names(&load_config());
+            if changed == names {
+                return None;
+            }
+            log::info!("Registering the changed hotkeys");
+            names = changed;
+            Some(parse_hotkeys(&names))
+        };
+        let result = tray::run(keys, on_hotkey, run_tray_command, reload_hotkeys);
+        match result {
+            Ok(()) => process::exit(0),
+            Err(err) => log::error!("Couldn't show the tray icon: {}", err),
+        }
+    });
+}
+
+fn run_tray_command(command: tray::Command) {
+    match command {
+        tray::Command::Show => run_hotkey(&[]),
+        tray::Command::Reindex => run_hotkey(&[String::from("index")]),
+        tray::Command::OpenConfig => match editable_config_path() {
+            Ok(path) => {
+                if let Err(err) = launch::launch(path, &[], &LaunchOptions::default()) {
+                    log::error!("Couldn't open \"{}\": {}", path, err);
+                }
+            }
+            Err(err) => log::error!("{}", err),
+        },
+    }
+}
+
+// `run` and `show` menus are shown by the daemon itself, other subcommands are started like a
+// hotkey tool would
+fn run_hotkey(args: &[String]) {
+    let command = args.first().map_or("run", String::as_str);
+    if command == "run" || command == "show" {
+        let wlines_args = args.get(1..).unwrap_or_default();
+        if let Err(err) = pipe::send(&serde_json::to_string(wlines_args).unwrap()) {
+            // The pipe is busy while the daemon's menu is open, which is brought to the front
+            // instead, like `show` does
+            if instance::acquire().is_some() {
+                log::error!("Couldn't show the menu: {}", err);
+            }
+        }
+    } else {
+        let exe = env::current_exe().unwrap_or_default();
+        if let Err(err) = launch::spawn_background(&exe.to_string_lossy(), args) {
+            log::error!("Couldn't start `wlines_run {}`: {}", command, err);
+        }
+    }
+}
+
+// Ask the daemon to show the menu, or show it from here if there's no daemon running
+fn cmd_show(wlines_args: Vec<String>) -> Result<(), Error> {
+    if let Err(err) = pipe::send(&serde_json::to_string(&wlines_args).unwrap()) {
+        log::debug!("No daemon to show the menu ({})", err);
+        return cmd_run(RunFlags::default(), wlines_args);
+    }
+    Ok(())
+}
+
+// Print the path of the best ranked program matching all words of the query, like the menu
+// would filter it, without launching anything
+fn cmd_which(query: &[String]) -> Result<(), Error> {
+    let mut programs = load_index();
+    let history_path = default_history_path();
+    let history = merge_synced_histories(&history_path, &load_history(&history_path));
+    let config = load_config();
+    let query = fold_diacritics(&query.join(" ")).to_lowercase();
+
+    let program = if let Some((program, _)) = resolve_alias(&query, &config, &programs) {
+        Some(program.clone())
+    } else {
+        Ranking::new(&config, &history, unix_time())?.sort(&mut programs);
+        programs.into_iter().find(|program| {
+            let search_terms = program_search_terms(&config, program);
+            let name = format_program_display_name(program, &search_terms, config.show_source);
+            let name = fold_diacritics(&name).to_lowercase();
+            query.split_whitespace().all(|word| name.contains(word))
+        })
+    };
+    match program {
+        Some(program) => {
+            println!("{}", program.abs_path);
+            Ok(())
+        }
+        None => Err(Error::NoMatch(query)),
+    }
+}
+
+// Show lines from stdin in wlines ranked by how often they were picked before, like dmenu with
+// a frecency history of its own for each menu name
+fn cmd_menu(name: &str, wlines_args: &[String]) -> Result<(), Error> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::InvalidMenuName);
+    }
+    let history_path = DATA_DIR.clone() + "/wlines_run_menu_" + name + ".json";
+    let mut history: HashMap<String, HistoryEntry> = try_load_data(&history_path)?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|source| Error::Read {
+            path: String::from("stdin"),
+            source,
+        })?;
+    let mut seen = HashSet::new();
+    let mut lines: Vec<String> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty() && seen.insert(*line))
+        .map(String::from)
+        .collect();
+
+    // Lines picked before come first, the rest keep their order
+    let time_now = unix_time();
+    let score = |line: &String| history.get(line).map(|entry| frecency(entry, time_now));
+    lines.sort_by(|a, b| match (score(a), score(b)) {
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+
+    let output = try_prompt(wlines_args, &lines)?.ok_or(Error::Cancelled)?;
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        println!("{}", line);
+        history
+            .entry(line.to_string())
+            .or_default()
+            .record_launch(time_now);
+    }
+
+    let history_json_data = serde_json::to_string(&history).unwrap();
+    try_write_data(&history_path, history_json_data)
+}
+
+fn cmd_power(wlines_args: &[String]) {
+    let config = load_config();
+    let labels: Vec<String> = POWER_MENU
+        .iter()
+        .map(|(label, _)| label.to_string())
+        .collect();
+    let choice = match prompt(wlines_args, &labels) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let shutdown_args = match POWER_MENU.iter().find(|(label, _)| *label == choice) {
+        Some((_, shutdown_args)) => *shutdown_args,
+        None => {
+            log::info!("Unknown choice '{}'", choice);
+            return;
+        }
+    };
+
+    // Locking loses nothing, everything else might
+    if config.confirm_power_actions && choice != "Lock" {
+        let confirmation = format!("Yes, {}", choice.to_lowercase());
+        let choices = [confirmation.clone(), String::from("No")];
+        if prompt(wlines_args, &choices).as_deref() != Some(confirmation.as_str()) {
+            return;
+        }
+    }
+
+    let result = match (choice.as_str(), shutdown_args) {
+        ("Sleep", _) => power::sleep(),
+        ("Lock", _) => power::lock(),
+        (_, Some(shutdown_args)) => {
+            // Restarting to the firmware settings needs administrator rights
+            let options = LaunchOptions {
+                verb: if shutdown_args.contains("/fw") {
+                    Some("runas")
+                } else {
+                    None
+                },
+                ..LaunchOptions::default()
+            };
+            launch::run_command_line(&format!("shutdown.exe {}", shutdown_args), &options)
+        }
+        _ => unreachable!(),
+    };
+    if let Err(err) = result {
+        report_error(
+            wlines_args,
+            &format!("Couldn't {}: {}", choice.to_lowercase(), err),
+        );
+    }
+}
+
+// Copy text picked from a menu, or type it into the window that had focus before the menu
+fn deliver_text(wlines_args: &[String], text: &str, type_it: bool) {
+    let result = if type_it {
+        // Give focus a moment to return from wlines
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        keyboard::type_text(text)
+    } else {
+        clipboard::set_text(text)
+    };
+    if let Err(err) = result {
+        let action = if type_it { "type" } else { "copy" };
+        report_error(wlines_args, &format!("Couldn't {} text: {}", action, err));
+    }
+}
+
+// Pick an emoji by name from the embedded list
+fn cmd_emoji(type_it: bool, wlines_args: &[String]) {
+    let lines: Vec<String> = EMOJI
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let (emoji, name, keywords) = (fields.next()?, fields.next()?, fields.next()?);
+            Some(format!("{}  {} [{}]", emoji, name, keywords))
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    match lines.iter().find(|line| **line == choice) {
+        Some(line) => deliver_text(wlines_args, line.split(' ').next().unwrap(), type_it),
+        None => log::info!("Unknown emoji '{}'", choice),
+    }
+}
+
+// Character for a code point like `U+2192` or `2192`
+fn parse_code_point(text: &str) -> Option<char> {
+    let hex = text
+        .trim()
+        .trim_start_matches("U+")
+        .trim_start_matches("u+");
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+// Pick a Unicode symbol by name from the embedded list, or any character by its code point
+fn cmd_symbol(type_it: bool, wlines_args: &[String]) {
+    let lines: Vec<String> = SYMBOLS
+        .lines()
+        .filter_map(|line| {
+            let (code_point, name) = line.split_once('\t')?;
+            let symbol = parse_code_point(code_point)?;
+            Some(format!("{}  {} U+{}", symbol, name, code_point))
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let code_point = if lines.contains(&choice) {
+        choice.rsplit(' ').next().unwrap()
+    } else {
+        choice.as_str()
+    };
+    match parse_code_point(code_point) {
+        Some(symbol) => deliver_text(wlines_args, &symbol.to_string(), type_it),
+        None => log::info!("Unknown symbol '{}'", choice),
+    }
+}
+
+// Pick one of the configured snippets by name
+fn cmd_snippet(type_it: bool, wlines_args: &[String]) {
+    let config = load_config();
+    let mut names: Vec<String> = config.snippets.keys().cloned().collect();
+    names.sort_by(|a, b| collate(a, b));
+    let choice = match prompt(wlines_args, &names) {
+        Some(choice) => choice,
+        None => return,
+    };
+    match config.snippets.get(&choice) {
+        Some(snippet) => deliver_text(wlines_args, snippet, type_it),
+        None => log::info!("Unknown snippet '{}'", choice),
+    }
+}
+
+fn notes_path(config: &Config) -> String {
+    if config.notes_file.is_empty() {
+        DATA_DIR.clone() + "/wlines_run_notes.txt"
+    } else {
+        config.notes_file.clone()
+    }
+}
+
+// Append whatever is typed to the notes file, one timestamped line per note
+fn cmd_note(wlines_args: &[String]) -> Result<(), Error> {
+    // Checked before asking, since the notes are appended to rather than written with `write_data`
+    if read_only() {
+        return Err(Error::Config(String::from(
+            "Notes can't be saved in read-only mode",
+        )));
+    }
+    let config = load_config();
+    let note = match labeled_prompt(wlines_args, "Note", &[]) {
+        Some(note) if !note.is_empty() => note,
+        _ => return Ok(()),
+    };
+    let path = notes_path(&config);
+    let line = format!("{}  {}\n", Local::now().format("%Y-%m-%d %H:%M"), note);
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|source| Error::Write { path, source })
+}
+
+// Browse the notes, most recent first, and copy or type the chosen one without its timestamp
+fn cmd_notes(type_it: bool, wlines_args: &[String]) {
+    let config = load_config();
+    let notes = fs::read_to_string(notes_path(&config)).unwrap_or_default();
+    let lines: Vec<String> = notes
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .map(String::from)
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let text = match choice.split_once("  ") {
+        Some((_, text)) => text,
+        None => &choice,
+    };
+    deliver_text(wlines_args, text, type_it);
+}
+
+fn load_dirs() -> Result<HashMap<String, HistoryEntry>, Error> {
+    try_load_data(&DIRS_PATH)
+}
+
+// Folders are tracked whenever they're opened through wlines_run, or added with `dirs add`
+fn record_dir(path: &str, current_time: u64) -> Result<(), Error> {
+    let path = path.trim_end_matches(['\\', '/']);
+    let mut dirs = load_dirs()?;
+    let key = dirs
+        .keys()
+        .find(|dir| dir.eq_ignore_ascii_case(path))
+        .cloned()
+        .unwrap_or_else(|| path.to_string());
+    dirs.entry(key).or_default().record_launch(current_time);
+    let dirs_json_data = serde_json::to_string(&dirs).unwrap();
+    try_write_data(&DIRS_PATH, dirs_json_data)
+}
+
+// Jump to a folder ranked by frecency, in Explorer or with `--terminal` in the configured terminal
+fn cmd_dirs(in_terminal: bool, wlines_args: &[String]) {
+    let time_now = unix_time();
+    let dirs = load_dirs().or_exit();
+    let mut lines: Vec<String> = dirs
+        .keys()
+        .filter(|dir| Path::new(dir).is_dir())
+        .cloned()
+        .collect();
+    lines.sort_by(|a, b| {
+        frecency(&dirs[b], time_now)
+            .partial_cmp(&frecency(&dirs[a], time_now))
+            .unwrap_or(Ordering::Equal)
+    });
+    let dir = match prompt(wlines_args, &lines) {
+        Some(dir) if !dir.is_empty() => dir.trim_matches('"').to_string(),
+        _ => return,
+    };
+
+    let config = load_config();
+    let result = if in_terminal {
+        let options = LaunchOptions {
+            working_dir: Some(&dir),
+            ..LaunchOptions::default()
+        };
+        launch::run_command_line(&config.directory_terminal, &options)
+    } else {
+        launch::launch(&dir, &[], &LaunchOptions::default())
+    };
+    match result {
+        Ok(()) => record_dir(&dir, time_now).or_exit(),
+        Err(err) => report_error(wlines_args, &format!("Couldn't open \"{}\": {}", dir, err)),
+    }
+}
+
+// Start, stop or restart a service, retrying as administrator when that's needed
+fn cmd_services(wlines_args: &[String]) {
+    let mut services = match services::list() {
+        Ok(services) => services,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't list services: {}", err));
+            return;
+        }
+    };
+    services.sort_by(|a, b| {
+        b.running
+            .cmp(&a.running)
+            .then_with(|| collate(&a.display_name, &b.display_name))
+    });
+    let lines: Vec<String> = services
+        .iter()
+        .map(|service| {
+            let state = if service.running {
+                "Running"
+            } else {
+                "Stopped"
+            };
+            format!("{} ({}) - {}", service.display_name, service.name, state)
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let service = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &services[i],
+        None => {
+            log::info!("Unknown service '{}'", choice);
+            return;
+        }
+    };
+
+    let actions: Vec<String> = if service.running {
+        vec![String::from("stop"), String::from("restart")]
+    } else {
+        vec![String::from("start")]
+    };
+    let action = match prompt(wlines_args, &actions) {
+        Some(action) => action,
+        None => return,
+    };
+    let command_line = match services::command_line(&action, &service.name) {
+        Some(command_line) => command_line,
+        None => {
+            log::info!("Unknown action '{}'", action);
+            return;
+        }
+    };
+
+    match services::control(&command_line) {
+        Ok(true) => log::info!("Ran {} on {}", action, service.display_name),
+        Ok(false) => {
+            let elevated = format!(
+                "Retry {} of {} as administrator",
+                action, service.display_name
+            );
+            if prompt(wlines_args, std::slice::from_ref(&elevated)).as_deref()
+                != Some(elevated.as_str())
+            {
+                return;
+            }
+            let options = LaunchOptions {
+                verb: Some("runas"),
+                ..LaunchOptions::default()
+            };
+            let command_line = format!("cmd.exe /c {}", command_line);
+            if let Err(err) = launch::run_command_line(&command_line, &options) {
+                report_error(wlines_args, &format!("Couldn't run net: {}", err));
+            }
+        }
+        Err(err) => report_error(wlines_args, &format!("Couldn't run net: {}", err)),
+    }
+}
+
+// Install a package from the winget catalog in a terminal window, reindexing afterwards so that
+// the new program shows up in the menu
+fn winget_install(query: &str, wlines_args: &[String], dry_run: bool) -> Result<(), Error> {
+    let packages = match winget::search(query) {
+        Ok(packages) => packages,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't run winget: {}", err));
+            return Ok(());
+        }
+    };
+    if packages.is_empty() {
+        report_error(wlines_args, &format!("No packages found for '{}'", query));
+        return Ok(());
+    }
+    let lines: Vec<String> = packages
+        .iter()
+        .map(|package| format!("{} ({}) {}", package.name, package.id, package.version))
+        .collect();
+    let choice = match try_prompt(wlines_args, &lines)? {
+        Some(choice) => choice,
+        None => return Ok(()),
+    };
+    let package = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &packages[i],
+        None => {
+            log::info!("Unknown package '{}'", choice);
+            return Ok(());
+        }
+    };
+
+    let exe = match own_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            report_error(wlines_args, &err.to_string());
+            return Ok(());
+        }
+    };
+    let command_line = format!(
+        "cmd.exe /s /c \"winget install --exact --id {} && \"{}\" index & pause\"",
+        package.id, exe
+    );
+    log::info!("Installing {}", package.id);
+    let options = LaunchOptions {
+        dry_run,
+        ..LaunchOptions::default()
+    };
+    if let Err(err) = launch::run_command_line(&command_line, &options) {
+        report_error(wlines_args, &format!("Couldn't run winget: {}", err));
+    }
+    Ok(())
+}
+
+// Copy the value of an environment variable. With `--split` the entries of lists like PATH are
+// shown one per line.
+fn cmd_env(split: bool, wlines_args: &[String]) {
+    let mut variables: Vec<(String, String)> = env::vars().collect();
+    variables.sort_by(|a, b| collate(&a.0, &b.0));
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for (name, value) in variables {
+        if split && value.contains(';') {
+            for (i, part) in value.split(';').filter(|part| !part.is_empty()).enumerate() {
+                entries.push((format!("{}[{}]: {}", name, i + 1, part), part.to_string()));
+            }
+        } else {
+            entries.push((format!("{}={}", name, value), value));
+        }
+    }
+
+    let lines: Vec<String> = entries.iter().map(|(line, _)| line.clone()).collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    match entries.iter().find(|(line, _)| *line == choice) {
+        Some((_, value)) => deliver_text(wlines_args, value, false),
+        None => log::info!("Unknown variable '{}'", choice),
+    }
+}
+
+// Switch the default playback or recording device
+fn cmd_audio(wlines_args: &[String]) {
+    let devices = match audio::list() {
+        Ok(devices) => devices,
+        Err(err) => {
+            report_error(
+                wlines_args,
+                &format!("Couldn't list audio devices: {}", err),
+            );
+            return;
+        }
+    };
+    let lines: Vec<String> = devices
+        .iter()
+        .map(|device| {
+            format!(
+                "{}{}: {}",
+                if device.is_default { "* " } else { "" },
+                if device.playback {
+                    "Playback"
+                } else {
+                    "Recording"
+                },
+                device.name
+            )
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let device = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &devices[i],
+        None => {
+            log::info!("Unknown device '{}'", choice);
+            return;
+        }
+    };
+    match audio::set_default(&device.id) {
+        Ok(()) => log::info!("Switched to {}", device.name),
+        Err(err) => report_error(
+            wlines_args,
+            &format!("Couldn't switch to {}: {}", device.name, err),
+        ),
+    }
+}
+
+// Switch to the chosen virtual desktop, or with `--move` move the focused window there
+fn cmd_desktops(move_window: bool, wlines_args: &[String]) {
+    let desktops = match desktops::list() {
+        Ok(desktops) => desktops,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't list desktops: {}", err));
+            return;
+        }
+    };
+    let lines: Vec<String> = desktops
+        .iter()
+        .map(|desktop| {
+            format!(
+                "{}{}: {}",
+                if desktop.current { "* " } else { "" },
+                desktop.number,
+                desktop.name
+            )
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let desktop = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &desktops[i],
+        None => {
+            log::info!("Unknown desktop '{}'", choice);
+            return;
+        }
+    };
+    let result = if move_window {
+        desktops::move_focused_window(desktop)
+    } else {
+        desktops::switch_to(&desktops, desktop)
+    };
+    if let Err(err) = result {
+        report_error(
+            wlines_args,
+            &format!("Couldn't use {}: {}", desktop.name, err),
+        );
+    }
+}
+
+fn unlock_vault(config: &Config, wlines_args: &[String]) -> Option<passwords::Vault> {
+    match config.password_manager.as_str() {
+        "keepassxc" => {
+            if config.keepassxc_database.is_empty() {
+                report_error(wlines_args, "No keepassxc_database is configured");
+                return None;
+            }
+            // Menus can't hide what is typed, which the README warns about
+            let master_password = labeled_prompt(wlines_args, "Master password", &[])?;
+            Some(passwords::Vault::KeePassXc {
+                database: config.keepassxc_database.clone(),
+                master_password,
+            })
+        }
+        "bitwarden" => {
+            if let Ok(session) = env::var("BW_SESSION") {
+                return Some(passwords::Vault::Bitwarden { session });
+            }
+            let master_password = labeled_prompt(wlines_args, "Master password", &[])?;
+            match passwords::Vault::unlock_bitwarden(&master_password) {
+                Ok(vault) => Some(vault),
+                Err(err) => {
+                    report_error(wlines_args, &format!("Couldn't unlock Bitwarden: {}", err));
+                    None
+                }
+            }
+        }
+        "" => {
+            report_error(wlines_args, "No password_manager is configured");
+            None
+        }
+        other => {
+            report_error(
+                wlines_args,
+                &format!("Unknown password_manager '{}'", other),
+            );
+            None
+        }
+    }
+}
+
+// Copy the password of the chosen entry, keeping it out of the clipboard history and clearing it
+// after a while. Nothing about the entry is recorded.
+fn cmd_pass(wlines_args: &[String]) {
+    let config = load_config();
+    let vault = match unlock_vault(&config, wlines_args) {
+        Some(vault) => vault,
+        None => return,
+    };
+    let entries = match vault.entries() {
+        Ok(entries) => entries,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't list passwords: {}", err));
+            return;
+        }
+    };
+    let titles: Vec<String> = entries.iter().map(|entry| entry.title.clone()).collect();
+    let choice = match prompt(wlines_args, &titles) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let entry = match entries.iter().find(|entry| entry.title == choice) {
+        Some(entry) => entry,
+        None => {
+            log::info!("Unknown entry '{}'", choice);
+            return;
+        }
+    };
+    let password = match vault.password(entry) {
+        Ok(password) => password,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't read the password: {}", err));
+            return;
+        }
+    };
+    let sequence_number = match clipboard::set_secret_text(&password) {
+        Ok(sequence_number) => sequence_number,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't copy the password: {}", err));
+            return;
+        }
+    };
+    if config.password_clear_seconds == 0 {
+        return;
+    }
+    let exe = match own_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            report_error(wlines_args, &err.to_string());
+            return;
+        }
+    };
+    let args = [
+        String::from("pass"),
+        String::from("--clear"),
+        sequence_number.to_string(),
+        config.password_clear_seconds.to_string(),
+    ];
+    match launch::spawn_background(&exe, &args) {
+        Ok(()) => log::info!(
+            "Copied, clearing in {} seconds",
+            config.password_clear_seconds
+        ),
+        Err(err) => report_error(
+            wlines_args,
+            &format!("Couldn't schedule clearing the clipboard: {}", err),
+        ),
+    }
+}
+
+fn cmd_pass_clear(sequence_number: u32, seconds: u64) -> Result<(), Error> {
+    std::thread::sleep(std::time::Duration::from_secs(seconds));
+    clipboard::clear_if_unchanged(sequence_number).map_err(|source| Error::Io {
+        action: "clear the clipboard",
+        source,
+    })
+}
+
+// Switch the projection mode like Win+P does, or apply one of the configured display presets
+fn cmd_display(wlines_args: &[String]) {
+    let config = load_config();
+    let mut presets: Vec<&String> = config.display_presets.keys().collect();
+    presets.sort_by(|a, b| collate(a, b));
+    let lines: Vec<String> = DISPLAY_MENU
+        .iter()
+        .map(|(label, _)| label.to_string())
+        .chain(presets.into_iter().cloned())
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let result =
+        if let Some((_, topology)) = DISPLAY_MENU.iter().find(|(label, _)| *label == choice) {
+            display::set_topology(*topology)
+        } else if let Some(preset) = config.display_presets.get(&choice) {
+            display::apply_preset(preset)
+        } else {
+            log::info!("Unknown display mode '{}'", choice);
+            return;
+        };
+    if let Err(err) = result {
+        report_error(
+            wlines_args,
+            &format!("Couldn't switch to {}: {}", choice, err),
+        );
+    }
+}
+
+// Connect to the chosen wireless network, asking for its key if it hasn't been saved
+fn cmd_wifi(wlines_args: &[String]) {
+    let networks = match wifi::list() {
+        Ok(networks) => networks,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't list networks: {}", err));
+            return;
+        }
+    };
+    if networks.is_empty() {
+        report_error(wlines_args, "No wireless networks are visible");
+        return;
+    }
+    let lines: Vec<String> = networks
+        .iter()
+        .map(|network| {
+            format!(
+                "{} - {}% ({})",
+                network.ssid, network.signal, network.authentication
+            )
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let network = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &networks[i],
+        None => {
+            log::info!("Unknown network '{}'", choice);
+            return;
+        }
+    };
+
+    match wifi::has_profile(&network.ssid) {
+        Ok(true) => {}
+        Ok(false) => {
+            // An empty key is for open networks
+            let label = format!("Key for {}", network.ssid);
+            let key = match labeled_prompt(wlines_args, &label, &[]) {
+                Some(key) => key,
+                None => return,
+            };
+            match wifi::add_profile(network, &key) {
+                Ok(true) => {}
+                Ok(false) => {
+                    report_error(
+                        wlines_args,
+                        &format!("Couldn't save a profile for {}", network.ssid),
+                    );
+                    return;
+                }
+                Err(err) => {
+                    report_error(
+                        wlines_args,
+                        &format!("Couldn't save a profile for {}: {}", network.ssid, err),
+                    );
+                    return;
+                }
+            }
+        }
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't run netsh: {}", err));
+            return;
+        }
+    }
+    match wifi::connect(&network.ssid) {
+        Ok(true) => log::info!("Connecting to {}", network.ssid),
+        Ok(false) => report_error(
+            wlines_args,
+            &format!("Couldn't connect to {}", network.ssid),
+        ),
+        Err(err) => report_error(
+            wlines_args,
+            &format!("Couldn't connect to {}: {}", network.ssid, err),
+        ),
+    }
+}
+
+// Connect to or disconnect from the chosen VPN connection
+fn cmd_vpn(wlines_args: &[String]) {
+    let connections = match vpn::list() {
+        Ok(connections) => connections,
+        Err(err) => {
+            report_error(wlines_args, &format!("Couldn't list connections: {}", err));
+            return;
+        }
+    };
+    if connections.is_empty() {
+        report_error(wlines_args, "No VPN connections are configured");
+        return;
+    }
+    let lines: Vec<String> = connections
+        .iter()
+        .map(|connection| {
+            let state = if connection.connected {
+                "Connected"
+            } else {
+                "Disconnected"
+            };
+            format!("{} - {}", connection.name, state)
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let connection = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &connections[i],
+        None => {
+            log::info!("Unknown connection '{}'", choice);
+            return;
+        }
+    };
+
+    if connection.connected {
+        match vpn::disconnect(&connection.name) {
+            Ok(true) => log::info!("Disconnected from {}", connection.name),
+            Ok(false) => report_error(
+                wlines_args,
+                &format!("Couldn't disconnect from {}", connection.name),
+            ),
+            Err(err) => report_error(wlines_args, &format!("Couldn't run rasdial: {}", err)),
+        }
+        return;
+    }
+    match vpn::connect(&connection.name) {
+        Ok(true) => log::info!("Connected to {}", connection.name),
+        // Usually the credentials aren't saved, which the dialog asks for
+        Ok(false) => {
+            if let Err(err) = vpn::connect_with_dialog(&connection.name) {
+                report_error(wlines_args, &format!("Couldn't run rasphone: {}", err));
+            }
+        }
+        Err(err) => report_error(wlines_args, &format!("Couldn't run rasdial: {}", err)),
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Timer {
+    due: u64,
+    message: String,
+}
+
+fn load_timers() -> Vec<Timer> {
+    load_data(&TIMERS_PATH)
+}
+
+fn save_timers(timers: &[Timer]) {
+    let timers_json_data = serde_json::to_string(timers).unwrap();
+    write_data(&TIMERS_PATH, timers_json_data);
+}
+
+// Durations like `25m`, `1h30m` or `90s`, where a plain number is minutes
+fn parse_duration(text: &str) -> Option<u64> {
+    if let Ok(minutes) = text.parse::<u64>() {
+        return Some(minutes * 60);
+    }
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        seconds += number.parse::<u64>().ok()? * unit;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return None;
+    }
+    Some(seconds)
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        _ => format!("{}h{}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// Start a timer, e.g. `timer 25m tea`. Each timer is waited for by its own background process,
+// which only notifies if the timer is still pending then.
+fn cmd_timer_start(seconds: u64, message: &[String]) -> Result<(), Error> {
+    // The waiting process looks the timer up in the saved ones, so it would never go off
+    if read_only() {
+        return Err(Error::Config(String::from(
+            "Timers can't be saved in read-only mode",
+        )));
+    }
+    let message = if message.is_empty() {
+        String::from("Timer done")
+    } else {
+        message.join(" ")
+    };
+    let due = unix_time() + seconds;
+    let mut timers = load_timers();
+    timers.push(Timer {
+        due,
+        message: message.clone(),
+    });
+    save_timers(&timers);
+
+    let args = [
+        String::from("timer"),
+        String::from("--wait"),
+        due.to_string(),
+    ];
+    launch::spawn_background(&own_exe()?, &args).map_err(|source| Error::Io {
+        action: "start the timer",
+        source,
+    })?;
+    log::info!("'{}' in {}", message, format_duration(seconds));
+    Ok(())
+}
+
+fn cmd_timer_wait(due: u64) {
+    let now = unix_time();
+    if due > now {
+        std::thread::sleep(std::time::Duration::from_secs(due - now));
+    }
+    let mut timers = load_timers();
+    let timer = match timers.iter().position(|timer| timer.due == due) {
+        Some(i) => timers.remove(i),
+        // Cancelled
+        None => return,
+    };
+    save_timers(&timers);
+    let config = load_config();
+    if let Err(err) = notify::show("wlines_run", &timer.message, config.timer_sound) {
+        log::error!("Couldn't show the notification: {}", err);
+    }
+}
+
+// List pending timers and cancel the chosen one
+fn cmd_timers(wlines_args: &[String]) {
+    let mut timers = load_timers();
+    let now = unix_time();
+    timers.retain(|timer| timer.due > now);
+    if timers.is_empty() {
+        report_error(wlines_args, "No timers are pending");
+        return;
+    }
+    timers.sort_by_key(|timer| timer.due);
+    let lines: Vec<String> = timers
+        .iter()
+        .map(|timer| {
+            format!(
+                "{} - in {}",
+                timer.message,
+                format_duration(timer.due - now)
+            )
+        })
+        .collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let i = match lines.iter().position(|line| *line == choice) {
+        Some(i) => i,
+        None => {
+            log::info!("Unknown timer '{}'", choice);
+            return;
+        }
+    };
+    let cancel = format!("Cancel '{}'", timers[i].message);
+    if prompt(wlines_args, std::slice::from_ref(&cancel)).as_deref() != Some(cancel.as_str()) {
+        return;
+    }
+    timers.remove(i);
+    save_timers(&timers);
+    log::info!("Cancelled");
+}
+
+fn cmd_timer(args: &[String]) -> Result<(), Error> {
+    match args.first() {
+        Some(duration) => match parse_duration(duration) {
+            Some(seconds) => cmd_timer_start(seconds, &args[1..]),
+            None => Err(Error::InvalidDuration(duration.clone())),
+        },
+        None => {
+            cmd_timers(&[]);
+            Ok(())
+        }
+    }
+}
+
+// Most recent first
+fn load_clips() -> Result<Vec<String>, Error> {
+    try_load_data(&CLIPS_PATH)
+}
+
+// Clips are shown on one line each, shortened
+fn clip_display_line(clip: &str) -> String {
+    let line = clip.trim().replace("\r\n", " ⏎ ").replace('\n', " ⏎ ");
+    match line.char_indices().nth(MAX_CLIP_LINE) {
+        Some((i, _)) => line[..i].to_string() + "…",
+        None => line,
+    }
+}
+
+// Record text copied to the clipboard until the process is stopped
+fn cmd_clip_watch() -> Result<(), Error> {
+    watch_config();
+    log::info!("Recording clipboard history");
+    let result = clipboard::watch(|text| {
+        if text.trim().is_empty() {
+            return;
+        }
+        let mut clips = match load_clips() {
+            Ok(clips) => clips,
+            Err(err) => {
+                log::error!("{}", err);
+                return;
+            }
+        };
+        clips.retain(|clip| *clip != text);
+        clips.insert(0, text);
+        clips.truncate(load_config().clip_history_size);
+        let clips_json_data = serde_json::to_string(&clips).unwrap();
+        if let Err(err) = try_write_data(&CLIPS_PATH, clips_json_data) {
+            log::error!("{}", err);
+        }
+    });
+    result.map_err(|source| Error::Io {
+        action: "watch the clipboard",
+        source,
+    })
+}
+
+// Pick a recorded clip and put it back on the clipboard
+fn cmd_clip(wlines_args: &[String]) {
+    let clips = load_clips().or_exit();
+    let lines: Vec<String> = clips.iter().map(|clip| clip_display_line(clip)).collect();
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return,
+    };
+    let clip = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &clips[i],
+        // Typed text is copied as it is
+        None => &choice,
+    };
+    if let Err(err) = clipboard::set_text(clip) {
+        report_error(wlines_args, &format!("Couldn't copy: {}", err));
+    }
+}
+
+// Pick a running process in wlines and terminate it, as administrator if it's protected
+fn cmd_kill(wlines_args: &[String]) -> Result<(), Error> {
+    let mut processes = tasks::list().map_err(|source| Error::Io {
+        action: "list processes",
+        source,
+    })?;
+    processes.sort_by_key(|task| std::cmp::Reverse(task.memory_kb()));
+    let lines: Vec<String> = processes
+        .iter()
+        .map(|task| format!("{} ({}) {}", task.name, task.pid, task.memory))
+        .collect();
+
+    let choice = match prompt(wlines_args, &lines) {
+        Some(choice) => choice,
+        None => return Ok(()),
+    };
+    let task = match lines.iter().position(|line| *line == choice) {
+        Some(i) => &processes[i],
+        None => {
+            log::info!("Unknown process '{}'", choice);
+            return Ok(());
+        }
+    };
+
+    match tasks::kill(task.pid) {
+        Ok(true) => log::info!("Killed {} ({})", task.name, task.pid),
+        Ok(false) => {
+            let elevated_kill = format!("Kill {} as administrator", task.name);
+            if prompt(wlines_args, std::slice::from_ref(&elevated_kill)).as_deref()
+                != Some(elevated_kill.as_str())
+            {
+                return Ok(());
+            }
+            let options = LaunchOptions {
+                verb: Some("runas"),
+                ..LaunchOptions::default()
+            };
+            let command_line = format!("taskkill /f /pid {}", task.pid);
+            if let Err(err) = launch::run_command_line(&command_line, &options) {
+                report_error(wlines_args, &format!("Couldn't run taskkill: {}", err));
+            }
+        }
+        Err(err) => report_error(wlines_args, &format!("Couldn't run taskkill: {}", err)),
+    }
+    Ok(())
+}
+
+// Launch a path or indexed program without a menu, e.g. from a script or hotkey
+fn cmd_launch(
+    target: &str,
+    prog_args: &[String],
+    mut verb: Option<&str>,
+    flags: RunFlags,
+) -> Result<(), Error> {
+    let RunFlags {
+        dry_run,
+        history: history_path,
+        no_history,
+        ..
+    } = flags;
+
+    // Indexed programs are found by path or title, anything else is launched as a path
+    let programs = load_index();
+    let program = match programs
+        .iter()
+        .find(|program| program_matches(program, target))
+    {
+        Some(program) => program.clone(),
+        None => Program {
+            title: Path::new(target).file_name().map_or_else(
+                || target.to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            source: SourceType::Path,
+            abs_path: target.to_string(),
+            first_seen: 0,
+        },
+    };
+
+    let config = load_config();
+    let elevate = config
+        .run_as_admin
+        .iter()
+        .any(|target| program_matches(&program, target))
+        || pe::requires_administrator(&program_target(&program));
+    if verb.is_none() && elevate {
+        verb = Some("runas");
+    }
+
+    if let Err(source) = start_program(&config, &program, prog_args, verb, dry_run) {
+        if !dry_run && !no_history && config.record_history {
+            let history_path = history_path.unwrap_or_else(default_history_path);
+            let _lock = lock_history(&history_path);
+            let mut history = load_history(&history_path);
+            if let Some(entry) = history.get_mut(&program.abs_path) {
+                entry.failures += 1;
+                save_history(&history_path, &history, config.encrypt_history);
+            }
+        }
+        return Err(Error::Launch {
+            path: program.abs_path,
+            source,
+        });
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let arg_string = launch::join_args(prog_args);
+    run_post_launch_hook(&config, &program, &arg_string);
+    if no_history || !config.record_history || is_excluded_from_history(&config, &program) {
+        return Ok(());
+    }
+
+    let history_path = history_path.unwrap_or_else(default_history_path);
+    let _lock = lock_history(&history_path);
+    let mut history = load_history(&history_path);
+    let time_now = unix_time();
+    let entry = history.entry(program.abs_path.clone()).or_default();
+    entry.record_launch(time_now);
+    entry.remember_args(&arg_string);
+    tidy_history(&config, &mut history, time_now);
+    save_history(&history_path, &history, config.encrypt_history);
+    Ok(())
+}
+
+// Print the history, highest frecency first
+fn cmd_history_list() {
+    let history_path = default_history_path();
+    let history = merge_synced_histories(&history_path, &load_history(&history_path));
+    let time_now = unix_time();
+    let mut entries: Vec<(&String, &HistoryEntry)> = history.iter().collect();
+    entries.sort_by(|a, b| {
+        frecency(b.1, time_now)
+            .partial_cmp(&frecency(a.1, time_now))
+            .unwrap_or(Ordering::Equal)
+    });
+    for (path, entry) in entries {
+        let last_used = chrono::DateTime::from_timestamp(entry.access as i64, 0)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{:>8.2} {:>5}  {}  {}",
+            frecency(entry, time_now),
+            entry.rank,
+            last_used,
+            path
+        );
+    }
+}
+
+fn cmd_history_remove(path: &str) -> Result<(), Error> {
+    let history_path = default_history_path();
+    let _lock = lock_history(&history_path);
+    let mut history = load_history(&history_path);
+    let key = history
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(path))
+        .cloned();
+    match key {
+        Some(key) => {
+            history.remove(&key);
+            save_history(&history_path, &history, load_config().encrypt_history);
+            println!("Removed \"{}\" from the history", key);
+            Ok(())
+        }
+        None => Err(Error::NotInHistory(path.to_string())),
+    }
+}
+
+fn cmd_history_export(path: &str) {
+    let history = load_history(&default_history_path());
+    let time_now = unix_time();
+    let exported: HashMap<&String, ExportedEntry> = history
+        .iter()
+        .map(|(program, entry)| {
+            (
+                program,
+                ExportedEntry {
+                    entry,
+                    frecency: frecency(entry, time_now),
+                },
+            )
+        })
+        .collect();
+    let exported_json_data = serde_json::to_string_pretty(&exported).unwrap();
+    write_data(path, exported_json_data);
+    println!("Exported {} entries to \"{}\"", history.len(), path);
+}
+
+// Merge an exported history into this one. The exported frecency is ignored, since it's computed
+// again from the ranks and times.
+fn cmd_history_import(path: &str) -> Result<(), Error> {
+    let imported_json_data = fs::read_to_string(path).map_err(|source| Error::Read {
+        path: path.to_string(),
+        source,
+    })?;
+    let imported: HashMap<String, HistoryEntry> = parse_json(path, &imported_json_data)?;
+    let history_path = default_history_path();
+    let _lock = lock_history(&history_path);
+    let mut history = load_history(&history_path);
+    for (program, entry) in &imported {
+        history.entry(program.clone()).or_default().merge(entry);
+    }
+    save_history(&history_path, &history, load_config().encrypt_history);
+    println!("Imported {} entries from \"{}\"", imported.len(), path);
+    Ok(())
+}
+
+fn cmd_history_reset() {
+    let history_path = default_history_path();
+    let _lock = lock_history(&history_path);
+    save_history(
+        &history_path,
+        &HashMap::new(),
+        load_config().encrypt_history,
+    );
+    println!("Cleared the history");
+}
+
+fn format_date(time: u64) -> String {
+    if time == 0 {
+        return String::from("-");
+    }
+    chrono::DateTime::from_timestamp(time as i64, 0)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+// A report of the history: the most launched programs, and launches over time, per weekday and
+// per hour
+fn stats(history: &HashMap<String, HistoryEntry>, current_time: u64, top: usize) -> Vec<String> {
+    // Ranks are aged, while the hours count every launch since they were recorded
+    let launches =
+        |entry: &HistoryEntry| -> u32 { entry.hours.iter().sum::<u32>().max(entry.rank) };
+    let mut entries: Vec<(&String, &HistoryEntry)> = history.iter().collect();
+    entries.sort_by_key(|(path, entry)| (std::cmp::Reverse(launches(entry)), path.to_string()));
+
+    let total: u32 = entries.iter().map(|(_, entry)| launches(entry)).sum();
+    let first = entries
+        .iter()
+        .map(|(_, entry)| entry.first)
+        .fold(0, earliest_time);
+    let days = if first == 0 {
+        1.0
+    } else {
+        (current_time.saturating_sub(first) as f64 / 86400.0).max(1.0)
+    };
+    let mut lines = vec![format!(
+        "{} launches of {} programs since {}, {:.1} per day, {:.1} per week",
+        total,
+        history.len(),
+        format_date(first),
+        f64::from(total) / days,
+        f64::from(total) / days * 7.0
+    )];
+
+    lines.push(String::new());
+    lines.push(String::from("Most launched   first use   last use"));
+    for (path, entry) in entries.iter().take(top) {
+        lines.push(format!(
+            "{:>13}   {:<10}  {:<10}  {}",
+            launches(entry),
+            format_date(entry.first),
+            format_date(entry.access),
+            path
+        ));
+    }
+
+    lines.push(String::new());
+    let mut weekdays = [0; 7];
+    let mut hours = [0; 24];
+    for (_, entry) in &entries {
+        for (count, launches) in weekdays.iter_mut().zip(entry.weekdays.iter()) {
+            *count += launches;
+        }
+        for (count, launches) in hours.iter_mut().zip(entry.hours.iter()) {
+            *count += launches;
+        }
+    }
+    let names = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let by_weekday: Vec<String> = names
+        .iter()
+        .zip(weekdays.iter())
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect();
+    lines.push(String::from("By weekday: ") + &by_weekday.join("  "));
+    let by_hour: Vec<String> = hours
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(hour, count)| format!("{:02} {}", hour, count))
+        .collect();
+    lines.push(String::from("By hour: ") + &by_hour.join("  "));
+    lines
+}
+
+fn cmd_stats(top: usize) {
+    let history_path = default_history_path();
+    let history = merge_synced_histories(&history_path, &load_history(&history_path));
+    for line in stats(&history, unix_time(), top) {
+        println!("{}", line);
+    }
+}
+
+// What `autostart install` starts at login without other arguments
+const AUTOSTART_ARGS: &[&str] = &["daemon"];
+
+fn cmd_autostart_install(args: &[String]) -> Result<(), Error> {
+    let args: Vec<String> = if args.is_empty() {
+        AUTOSTART_ARGS.iter().map(|arg| arg.to_string()).collect()
+    } else {
+        args.to_vec()
+    };
+    let exe = env::current_exe().unwrap_or_default();
+    let command_line = format!("\"{}\" {}", exe.display(), launch::join_args(&args));
+    autostart::install(&command_line).map_err(|source| Error::Io {
+        action: "set up autostart",
+        source,
+    })?;
+    println!("Starting {} at login", command_line);
+    Ok(())
+}
+
+fn cmd_autostart_remove() -> Result<(), Error> {
+    if autostart::installed().is_none() {
+        println!("wlines_run isn't started at login");
+        return Ok(());
+    }
+    autostart::remove().map_err(|source| Error::Io {
+        action: "remove autostart",
+        source,
+    })?;
+    println!("No longer starting wlines_run at login");
+    Ok(())
+}
+
+fn cmd_hide(path: &str) -> Result<(), Error> {
+    add_to_list(&BLACKLIST_PATH, path)?;
+    println!("Hid \"{}\"", path);
+    Ok(())
+}
+
+fn cmd_unhide(path: &str) -> Result<(), Error> {
+    remove_from_list(&BLACKLIST_PATH, path)?;
+    println!("Unhid \"{}\", reindex to show it again", path);
+    Ok(())
+}
+
+fn main() {
+    use clap::{CommandFactory, FromArgMatches, Parser};
+    use cli::{AutostartCommand, Command as Cmd, DirsCommand, HistoryCommand};
+
+    let matches = cli::Cli::command().get_matches();
+    let cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if cli.portable {
+        env::set_var(PORTABLE_VAR, "1");
+    }
+    if cli.read_only {
+        env::set_var(READ_ONLY_VAR, "1");
+    }
+    if let Some(path) = &cli.index_file {
+        env::set_var(INDEX_VAR, path);
+    }
+    if let Some(path) = &cli.history_file {
+        env::set_var(HISTORY_VAR, path);
+    }
+    // Errors can only go to stderr until the log in the data folder is open
+    if let Err(err) = data_dir() {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
+    }
+    logging::init(&LOG_PATH, cli.verbose);
+    if let Some(path) = cli.wlines_path {
+        let _ = WLINES_PATH.set(path);
+    }
+    if let Some(mode) = matches.subcommand_name() {
+        // The daemon's menus are the ones of `run`
+        let mode = match mode {
+            "daemon" | "show" => "run",
+            mode => mode,
+        };
+        let _ = MODE.set(mode.to_string());
+    }
+    match cli.command {
+        Cmd::Index => cmd_index().or_exit(),
+        Cmd::Setup(wlines) => cmd_setup(&wlines.wlines_args).or_exit(),
+        Cmd::Daemon => cmd_daemon().or_exit(),
+        Cmd::Show(wlines) => cmd_show(wlines.wlines_args).or_exit(),
+        Cmd::Run(run) => {
+            let (flags, wlines_args) = split_run_args(run);
+            cmd_run(flags, wlines_args).or_exit();
+        }
+        Cmd::Open { file, run } => {
+            let (flags, wlines_args) = split_run_args(run);
+            cmd_open(&file, flags, wlines_args).or_exit();
+        }
+        Cmd::Launch {
+            dry_run,
+            admin,
+            history,
+            no_history,
+            verb,
+            target,
+            args,
+        } => {
+            let verb = if admin {
+                Some("runas")
+            } else {
+                verb.as_deref()
+            };
+            let flags = RunFlags {
+                dry_run,
+                open_file: None,
+                history,
+                no_history,
+                reindex: false,
+            };
+            cmd_launch(&target, &args, verb, flags).or_exit();
+        }
+        Cmd::Which { query } => cmd_which(&query).or_exit(),
+        Cmd::Menu { name, wlines } => cmd_menu(&name, &wlines.wlines_args).or_exit(),
+        Cmd::Kill(wlines) => cmd_kill(&wlines.wlines_args).or_exit(),
+        Cmd::Power(wlines) => cmd_power(&wlines.wlines_args),
+        Cmd::Desktops {
+            move_window,
+            wlines,
+        } => cmd_desktops(move_window, &wlines.wlines_args),
+        Cmd::Pass { clear, wlines } => match clear.as_deref() {
+            Some(&[sequence_number, seconds]) => {
+                cmd_pass_clear(sequence_number as u32, seconds).or_exit()
+            }
+            Some(_) => unreachable!(),
+            None => cmd_pass(&wlines.wlines_args),
+        },
+        Cmd::Display(wlines) => cmd_display(&wlines.wlines_args),
+        Cmd::Wifi(wlines) => cmd_wifi(&wlines.wlines_args),
+        Cmd::Vpn(wlines) => cmd_vpn(&wlines.wlines_args),
+        Cmd::Timer { wait, args } => match wait {
+            Some(due) => cmd_timer_wait(due),
+            None => cmd_timer(&args).or_exit(),
+        },
+        Cmd::Audio(wlines) => cmd_audio(&wlines.wlines_args),
+        Cmd::Services(wlines) => cmd_services(&wlines.wlines_args),
+        Cmd::Env { split, wlines } => cmd_env(split, &wlines.wlines_args),
+        Cmd::Winget {
+            query,
+            dry_run,
+            wlines,
+        } => winget_install(&query, &wlines.wlines_args, dry_run).or_exit(),
+        Cmd::Clip { watch: true, .. } => cmd_clip_watch().or_exit(),
+        Cmd::Clip { wlines, .. } => cmd_clip(&wlines.wlines_args),
+        Cmd::Emoji(args) => cmd_emoji(args.type_it, &args.wlines.wlines_args),
+        Cmd::Symbol(args) => cmd_symbol(args.type_it, &args.wlines.wlines_args),
+        Cmd::Snippet(args) => cmd_snippet(args.type_it, &args.wlines.wlines_args),
+        Cmd::Note(wlines) => cmd_note(&wlines.wlines_args).or_exit(),
+        Cmd::Notes(args) => cmd_notes(args.type_it, &args.wlines.wlines_args),
+        Cmd::Dirs {
+            action: Some(DirsCommand::Add { path }),
+            ..
+        } => record_dir(&path, unix_time()).or_exit(),
+        Cmd::Dirs {
+            terminal, wlines, ..
+        } => cmd_dirs(terminal, &wlines.wlines_args),
+        Cmd::History { action } => match action {
+            HistoryCommand::List => cmd_history_list(),
+            HistoryCommand::Remove { path } => cmd_history_remove(&path).or_exit(),
+            HistoryCommand::Export { file } => cmd_history_export(&file),
+            HistoryCommand::Import { file } => cmd_history_import(&file).or_exit(),
+            HistoryCommand::Reset => cmd_history_reset(),
+        },
+        Cmd::Stats { count } => cmd_stats(count),
+        Cmd::Completions { shell } => clap_complete::generate(
+            shell,
+            &mut cli::Cli::command(),
+            "wlines_run",
+            &mut io::stdout(),
+        ),
+        Cmd::Autostart { action } => match action {
+            AutostartCommand::Install { args } => cmd_autostart_install(&args).or_exit(),
+            AutostartCommand::Remove => cmd_autostart_remove().or_exit(),
+        },
+        Cmd::Hide { path } => cmd_hide(&path).or_exit(),
+        Cmd::Unhide { path } => cmd_unhide(&path).or_exit(),
+        Cmd::External(args) => match args[0].strip_prefix("menu:") {
+            Some(name) => {
+                let menu_args = cli::CustomMenuArgs::parse_from(&args);
+                cmd_custom_menu(name, menu_args.dry_run, &menu_args.wlines.wlines_args).or_exit();
+            }
+            None => cli::Cli::command()
+                .error(
+                    clap::error::ErrorKind::InvalidSubcommand,
+                    format!("unrecognized subcommand '{}'", args[0]),
+                )
+                .exit(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(title: &str, abs_path: &str) -> Program {
+        Program {
+            title: String::from(title),
+            source: SourceType::StartMenu,
+            abs_path: String::from(abs_path),
+            first_seen: 0,
+        }
+    }
+
+    #[test]
+    fn selection_with_arguments() {
+        let firefox = program("Firefox.lnk", "C:\\Firefox.lnk");
+        let links = vec![(String::from("S] Firefox.lnk"), &firefox)];
+        let (chosen, args) = match_selection("S] Firefox.lnk: -private-window", &links).unwrap();
+        assert_eq!(chosen.abs_path, firefox.abs_path);
+        assert_eq!(args, " -private-window");
+    }
+
+    #[test]
+    fn selection_of_title_containing_colon() {
+        let foo = program("Foo", "C:\\Foo.lnk");
+        let foo_bar = program("Foo: Bar", "C:\\Foo Bar.lnk");
+        let links = vec![
+            (String::from("S] Foo"), &foo),
+            (String::from("S] Foo: Bar"), &foo_bar),
+        ];
+
+        let (chosen, args) = match_selection("S] Foo: Bar: -x", &links).unwrap();
+        assert_eq!(chosen.abs_path, foo_bar.abs_path);
+        assert_eq!(args, " -x");
+
+        // Without a colon after "Bar" it can only be arguments to "Foo"
+        let (chosen, args) = match_selection("S] Foo: Bar", &links).unwrap();
+        assert_eq!(chosen.abs_path, foo.abs_path);
+        assert_eq!(args, " Bar");
+    }
+
+    #[test]
+    fn selection_of_title_prefixing_another() {
+        let code = program("Code.lnk", "C:\\Code.lnk");
+        let insiders = program("Code.lnk Insiders", "C:\\Code Insiders.lnk");
+        let links = vec![
+            (String::from("S] Code.lnk"), &code),
+            (String::from("S] Code.lnk Insiders"), &insiders),
+        ];
+        let (chosen, _) = match_selection("S] Code.lnk Insiders:", &links).unwrap();
+        assert_eq!(chosen.abs_path, insiders.abs_path);
+        let (chosen, _) = match_selection("S] Code.lnk:", &links).unwrap();
+        assert_eq!(chosen.abs_path, code.abs_path);
+    }
+
+    #[test]
+    fn selection_of_unicode_title() {
+        let cafe = program("Café [ünï] (x86).lnk", "C:\\Café.lnk");
+        let links = vec![(String::from("S] Café [ünï] (x86).lnk"), &cafe)];
+        let (chosen, args) = match_selection("S] Café [ünï] (x86).lnk:", &links).unwrap();
+        assert_eq!(chosen.abs_path, cafe.abs_path);
+        assert_eq!(args, "");
+        assert!(match_selection("S] Café", &links).is_none());
+    }
+
+    #[test]
+    fn duplicate_titles_are_made_unique() {
+        let first = program("notepad.exe", "C:/Windows/notepad.exe");
+        let second = program("notepad.exe", "C:/Windows/System32/notepad.exe");
+        let mut links = vec![
+            (String::from("P] notepad.exe"), &first),
+            (String::from("P] notepad.exe"), &second),
+        ];
+        make_display_names_unique(&mut links);
+        assert_eq!(links[0].0, "P] notepad.exe (C:/Windows)");
+        assert_eq!(links[1].0, "P] notepad.exe (C:/Windows/System32)");
+
+        let (chosen, _) = match_selection("P] notepad.exe (C:/Windows/System32):", &links).unwrap();
+        assert_eq!(chosen.abs_path, second.abs_path);
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches(
+            "*uninstall*",
+            "Programs/Uninstall Firefox.lnk"
+        ));
+        assert!(glob_matches("*.bat", "C:\\Tools\\reboot.bat"));
+        assert!(glob_matches("shutdown.???", "Shutdown.exe"));
+        assert!(!glob_matches("*.bat", "C:\\Tools\\reboot.bat.txt"));
+        assert!(!glob_matches("shut*x", "shutdown.exe"));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("25"), Some(1500));
+        assert_eq!(parse_duration("25m"), Some(1500));
+        assert_eq!(parse_duration("1h30m"), Some(5400));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("tea"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("1h30"), None);
+    }
+
+    #[test]
+    fn history_is_capped_by_frecency() {
+        let entry = |rank, access| HistoryEntry {
+            rank,
+            access,
+            ..HistoryEntry::default()
+        };
+        let mut history = HashMap::new();
+        history.insert(String::from("often"), entry(50, 1000));
+        history.insert(String::from("once long ago"), entry(1, 0));
+        history.insert(String::from("recent"), entry(2, 1000));
+        cap_history(&mut history, 2, 1000);
+        assert_eq!(history.len(), 2);
+        assert!(!history.contains_key("once long ago"));
+
+        cap_history(&mut history, 0, 1000);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn ranks_are_aged() {
+        let entry = |rank| HistoryEntry {
+            rank,
+            ..HistoryEntry::default()
+        };
+        let mut history = HashMap::new();
+        history.insert(String::from("daily"), entry(100));
+        history.insert(String::from("once"), entry(1));
+        age_history(&mut history, 200);
+        assert_eq!(history["daily"].rank, 100);
+
+        age_history(&mut history, 50);
+        assert_eq!(history["daily"].rank, 90);
+        assert!(!history.contains_key("once"));
+    }
+
+    #[test]
+    fn history_entries_are_merged() {
+        let mut entry = HistoryEntry {
+            rank: 3,
+            access: 100,
+            args: vec![String::from("-a")],
+            ..HistoryEntry::default()
+        };
+        entry.hours[9] = 3;
+        let mut other = HistoryEntry {
+            rank: 2,
+            access: 50,
+            args: vec![String::from("-b"), String::from("-a")],
+            ..HistoryEntry::default()
+        };
+        other.hours[9] = 2;
+        entry.merge(&other);
+        assert_eq!(entry.rank, 5);
+        assert_eq!(entry.access, 100);
+        assert_eq!(entry.hours[9], 5);
+        assert_eq!(entry.args, ["-a", "-b"]);
+    }
+
+    #[test]
+    fn failures_lower_frecency() {
+        let mut entry = HistoryEntry {
+            rank: 4,
+            access: 100,
+            ..HistoryEntry::default()
+        };
+        let working = frecency(&entry, 100);
+        entry.failures = 2;
+        assert_eq!(frecency(&entry, 100), working / 4.0);
+        entry.record_launch(200);
+        assert_eq!(entry.failures, 0);
+    }
+
+    #[test]
+    fn toml_config() {
+        let config: Config = toml::from_str(
+            r#"
+index_dirs = ['C:\Tools']
+tie_breakers = ["shortest_title"]
+context_weight = 2.0
+
+[aliases]
+ff = "Firefox.lnk"
+
+[window_states]
+"Notepad.lnk" = "maximized"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.index_dirs, ["C:\\Tools"]);
+        assert_eq!(config.aliases["ff"], "Firefox.lnk");
+        assert_eq!(config.context_weight, 2.0);
+        assert_eq!(config.extensions, DEFAULT_EXTENSIONS);
+    }
+
+    #[test]
+    fn stats_report() {
+        let mut firefox = HistoryEntry {
+            first: 86400,
+            ..HistoryEntry::default()
+        };
+        firefox.hours[9] = 3;
+        firefox.weekdays[0] = 3;
+        let notepad = HistoryEntry {
+            rank: 1,
+            ..HistoryEntry::default()
+        };
+        let history = HashMap::from([
+            (String::from("firefox.lnk"), firefox),
+            (String::from("notepad.lnk"), notepad),
+        ]);
+        let lines = stats(&history, 86400 * 5, 1);
+        assert!(lines[0].starts_with("4 launches of 2 programs since "));
+        assert!(lines[0].ends_with(", 1.0 per day, 7.0 per week"));
+        assert!(lines[3].ends_with("firefox.lnk"));
+        assert!(lines[3].trim_start().starts_with("3 "));
+        assert_eq!(
+            lines[5],
+            "By weekday: Mon 3  Tue 0  Wed 0  Thu 0  Fri 0  Sat 0  Sun 0"
+        );
+        assert_eq!(lines[6], "By hour: 09 3");
+    }
+
+    #[test]
+    fn concurrent_launches_are_kept() {
+        let original_entry = HistoryEntry {
+            rank: 2,
+            access: 100,
+            args: vec![String::from("-a")],
+            ..HistoryEntry::default()
+        };
+        let original = HashMap::from([
+            (String::from("C:\\a.exe"), original_entry),
+            (String::from("C:\\gone.exe"), HistoryEntry::default()),
+        ]);
+
+        // Another instance launched a.exe and b.exe in the meantime
+        let mut current = original.clone();
+        current.get_mut("C:\\a.exe").unwrap().rank = 3;
+        current.insert(String::from("C:\\b.exe"), HistoryEntry::default());
+
+        // This one launched a.exe with other arguments and pruned gone.exe
+        let mut changed = original.clone();
+        changed.remove("C:\\gone.exe");
+        let entry = changed.get_mut("C:\\a.exe").unwrap();
+        entry.rank = 3;
+        entry.access = 200;
+        entry.remember_args("-b");
+
+        apply_history_changes(&mut current, &original, &changed);
+        let entry = &current["C:\\a.exe"];
+        assert_eq!(entry.rank, 4);
+        assert_eq!(entry.access, 200);
+        assert_eq!(entry.args, ["-b", "-a"]);
+        assert!(current.contains_key("C:\\b.exe"));
+        assert!(!current.contains_key("C:\\gone.exe"));
+    }
+
+    #[test]
+    fn url_detection() {
+        assert_eq!(
+            input_url("github.com/foo").as_deref(),
+            Some("https://github.com/foo")
+        );
+        assert_eq!(
+            input_url("http://localhost:8080").as_deref(),
+            Some("http://localhost:8080")
+        );
+        assert_eq!(input_url("notepad.exe"), None);
+        assert_eq!(input_url("firefox"), None);
+        assert_eq!(input_url("1.5"), None);
+        assert_eq!(input_url("a.com b"), None);
     }
 }
@@ -0,0 +1,81 @@
+//! Desktop notifications, shown as a balloon from a temporary notification area icon
+
+use std::io;
+
+// Copy text into a fixed size buffer of the icon data, cutting it off if needed
+#[cfg(windows)]
+pub fn copy_wide(buffer: &mut [u16], text: &str) {
+    let wide: Vec<u16> = text.encode_utf16().take(buffer.len() - 1).collect();
+    buffer[..wide.len()].copy_from_slice(&wide);
+    buffer[wide.len()] = 0;
+}
+
+/// Show a notification, which Windows 10 and later display as a toast, optionally without its
+/// sound. Blocks until the notification has had time to be read.
+#[cfg(windows)]
+pub fn show(title: &str, message: &str, sound: bool) -> io::Result<()> {
+    use std::{mem, ptr, thread, time::Duration};
+    use winapi::um::shellapi::{
+        Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_TIP, NIIF_INFO, NIIF_NOSOUND, NIM_ADD,
+        NIM_DELETE, NOTIFYICONDATAW,
+    };
+    use winapi::um::winuser::{
+        CreateWindowExW, DestroyWindow, LoadIconW, HWND_MESSAGE, IDI_INFORMATION,
+    };
+
+    let class_name = crate::win::to_wide("STATIC");
+    unsafe {
+        // The icon needs a window, even though nothing is done with its messages
+        let window = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if window.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut data: NOTIFYICONDATAW = mem::zeroed();
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = window;
+        data.uID = 1;
+        data.uFlags = NIF_ICON | NIF_TIP | NIF_INFO;
+        data.hIcon = LoadIconW(ptr::null_mut(), IDI_INFORMATION);
+        data.dwInfoFlags = if sound {
+            NIIF_INFO
+        } else {
+            NIIF_INFO | NIIF_NOSOUND
+        };
+        copy_wide(&mut data.szTip, title);
+        copy_wide(&mut data.szInfoTitle, title);
+        copy_wide(&mut data.szInfo, message);
+
+        if Shell_NotifyIconW(NIM_ADD, &mut data) == 0 {
+            let error = io::Error::last_os_error();
+            DestroyWindow(window);
+            return Err(error);
+        }
+        // Removing the icon also removes the notification
+        thread::sleep(Duration::from_secs(10));
+        Shell_NotifyIconW(NIM_DELETE, &mut data);
+        DestroyWindow(window);
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn show(_title: &str, _message: &str, _sound: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Notifications are only supported on Windows",
+    ))
+}
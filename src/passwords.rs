@@ -0,0 +1,134 @@
+//! Reading passwords from KeePassXC and Bitwarden through their command line interfaces
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// An unlocked password database
+pub enum Vault {
+    KeePassXc {
+        database: String,
+        master_password: String,
+    },
+    Bitwarden {
+        session: String,
+    },
+}
+
+pub struct Entry {
+    pub title: String,
+    // Path in the database or item ID, for looking up its password
+    key: String,
+}
+
+// Run a command with an optional line on stdin, so that secrets aren't visible in its arguments
+fn run(command: &mut Command, input: Option<&str>) -> io::Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        if let Some(input) = input {
+            stdin.write_all(input.as_bytes())?;
+            stdin.write_all(b"\n")?;
+        }
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(if message.is_empty() {
+            String::from("The command failed")
+        } else {
+            message
+        }));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl Vault {
+    /// Unlock Bitwarden with the master password, for when there's no `BW_SESSION`
+    pub fn unlock_bitwarden(master_password: &str) -> io::Result<Vault> {
+        let session = run(
+            Command::new("bw")
+                .args(["unlock", "--raw", "--passwordenv", "WLINES_RUN_BW_PASSWORD"])
+                .env("WLINES_RUN_BW_PASSWORD", master_password),
+            None,
+        )?;
+        Ok(Vault::Bitwarden {
+            session: session.trim().to_string(),
+        })
+    }
+
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        match self {
+            // Entries are listed with their group path, and groups end with a `/`
+            Vault::KeePassXc {
+                database,
+                master_password,
+            } => {
+                let output = run(
+                    Command::new("keepassxc-cli").args(["ls", "-R", "-f", "-q", database]),
+                    Some(master_password),
+                )?;
+                Ok(output
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.ends_with('/'))
+                    .map(|line| Entry {
+                        title: line.to_string(),
+                        key: line.to_string(),
+                    })
+                    .collect())
+            }
+            Vault::Bitwarden { session } => {
+                let output = run(
+                    Command::new("bw")
+                        .args(["list", "items", "--session", session])
+                        .env("BW_NOINTERACTION", "true"),
+                    None,
+                )?;
+                let items: Vec<serde_json::Value> = serde_json::from_str(&output)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                Ok(items
+                    .iter()
+                    .filter(|item| item["login"]["password"].is_string())
+                    .map(|item| {
+                        let name = item["name"].as_str().unwrap_or("");
+                        let title = match item["login"]["username"].as_str() {
+                            Some(username) if !username.is_empty() => {
+                                format!("{} ({})", name, username)
+                            }
+                            _ => name.to_string(),
+                        };
+                        Entry {
+                            title,
+                            key: item["id"].as_str().unwrap_or("").to_string(),
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    pub fn password(&self, entry: &Entry) -> io::Result<String> {
+        let output = match self {
+            Vault::KeePassXc {
+                database,
+                master_password,
+            } => run(
+                Command::new("keepassxc-cli")
+                    .args(["show", "-q", "-s", "-a", "password", database, &entry.key]),
+                Some(master_password),
+            )?,
+            Vault::Bitwarden { session } => run(
+                Command::new("bw")
+                    .args(["get", "password", &entry.key, "--session", session])
+                    .env("BW_NOINTERACTION", "true"),
+                None,
+            )?,
+        };
+        // The line break after the password would be pasted as Enter
+        Ok(output.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
@@ -0,0 +1,98 @@
+//! Reading the subsystem and manifest of executables from their PE headers
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+const IMAGE_SUBSYSTEM_WINDOWS_CUI: u16 = 3;
+
+// Offset of the Subsystem field from the start of the optional header, same for PE32 and PE32+
+const SUBSYSTEM_OFFSET: u64 = 68;
+
+fn read_u16(file: &mut File, offset: u64) -> io::Result<u16> {
+    let mut buffer = [0u8; 2];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(u16::from_le_bytes(buffer))
+}
+
+fn read_subsystem(path: &str) -> io::Result<u16> {
+    let mut file = File::open(path)?;
+    if read_u16(&mut file, 0)? != u16::from_le_bytes(*b"MZ") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not an executable",
+        ));
+    }
+
+    let mut buffer = [0u8; 4];
+    file.seek(SeekFrom::Start(0x3c))?;
+    file.read_exact(&mut buffer)?;
+    let pe_offset = u64::from(u32::from_le_bytes(buffer));
+
+    file.seek(SeekFrom::Start(pe_offset))?;
+    file.read_exact(&mut buffer)?;
+    if &buffer != b"PE\0\0" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a PE executable",
+        ));
+    }
+
+    // The optional header follows the 4 byte signature and the 20 byte file header
+    read_u16(&mut file, pe_offset + 24 + SUBSYSTEM_OFFSET)
+}
+
+/// Whether the file is an executable built for the console subsystem
+pub fn is_console_program(path: &str) -> bool {
+    read_subsystem(path).ok() == Some(IMAGE_SUBSYSTEM_WINDOWS_CUI)
+}
+
+// Text of the executable's embedded application manifest
+#[cfg(windows)]
+fn read_manifest(path: &str) -> Option<String> {
+    use crate::win::to_wide;
+    use std::{ptr, slice};
+    use winapi::um::libloaderapi::{
+        FindResourceW, FreeLibrary, LoadLibraryExW, LoadResource, LockResource, SizeofResource,
+        LOAD_LIBRARY_AS_DATAFILE, LOAD_LIBRARY_AS_IMAGE_RESOURCE,
+    };
+    use winapi::um::winuser::{CREATEPROCESS_MANIFEST_RESOURCE_ID, RT_MANIFEST};
+
+    let path = to_wide(path);
+    unsafe {
+        let module = LoadLibraryExW(
+            path.as_ptr(),
+            ptr::null_mut(),
+            LOAD_LIBRARY_AS_DATAFILE | LOAD_LIBRARY_AS_IMAGE_RESOURCE,
+        );
+        if module.is_null() {
+            return None;
+        }
+        let resource = FindResourceW(module, CREATEPROCESS_MANIFEST_RESOURCE_ID, RT_MANIFEST);
+        let mut manifest = None;
+        if !resource.is_null() {
+            let data = LockResource(LoadResource(module, resource));
+            if !data.is_null() {
+                let bytes = slice::from_raw_parts(
+                    data as *const u8,
+                    SizeofResource(module, resource) as usize,
+                );
+                manifest = Some(String::from_utf8_lossy(bytes).into_owned());
+            }
+        }
+        FreeLibrary(module);
+        manifest
+    }
+}
+
+#[cfg(not(windows))]
+fn read_manifest(_path: &str) -> Option<String> {
+    None
+}
+
+/// Whether the executable's manifest asks to always be run as administrator
+pub fn requires_administrator(path: &str) -> bool {
+    read_manifest(path).is_some_and(|manifest| {
+        manifest.contains("\"requireAdministrator\"") || manifest.contains("'requireAdministrator'")
+    })
+}
@@ -0,0 +1,107 @@
+//! The named pipe that `wlines_run show` asks a running daemon to show the menu through
+
+use std::io;
+
+// One pipe per user, so that each user's daemon only shows menus for them
+#[cfg(windows)]
+fn pipe_name() -> String {
+    format!(
+        "\\\\.\\pipe\\wlines_run-{}",
+        std::env::var("USERNAME").unwrap_or_default()
+    )
+}
+
+/// Call `on_message` with each message sent to the pipe, one client at a time, until an error
+#[cfg(windows)]
+pub fn serve(mut on_message: impl FnMut(String)) -> io::Result<()> {
+    use crate::win::to_wide;
+    use std::ptr;
+    use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+    use winapi::um::fileapi::ReadFile;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe};
+    use winapi::um::winbase::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+
+    let name = to_wide(&pipe_name());
+    loop {
+        let message = unsafe {
+            let pipe = CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                0,
+                4096,
+                0,
+                ptr::null_mut(),
+            );
+            if pipe == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            if ConnectNamedPipe(pipe, ptr::null_mut()) == 0
+                && io::Error::last_os_error().raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32)
+            {
+                let err = io::Error::last_os_error();
+                CloseHandle(pipe);
+                return Err(err);
+            }
+
+            // The client closes its end after writing the message
+            let mut message = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let mut read = 0;
+                let ok = ReadFile(
+                    pipe,
+                    buffer.as_mut_ptr() as *mut _,
+                    buffer.len() as u32,
+                    &mut read,
+                    ptr::null_mut(),
+                );
+                if ok == 0 || read == 0 {
+                    break;
+                }
+                message.extend_from_slice(&buffer[..read as usize]);
+            }
+            DisconnectNamedPipe(pipe);
+            CloseHandle(pipe);
+            message
+        };
+        on_message(String::from_utf8_lossy(&message).into_owned());
+    }
+}
+
+/// Send a message to the daemon, failing if none is running
+#[cfg(windows)]
+pub fn send(message: &str) -> io::Result<()> {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use winapi::um::winuser::{AllowSetForegroundWindow, ASFW_ANY};
+
+    let mut pipe = OpenOptions::new().write(true).open(pipe_name())?;
+    // Started from a hotkey this process may bring windows to the front, and the daemon's menu
+    // should be allowed to as well
+    unsafe {
+        AllowSetForegroundWindow(ASFW_ANY);
+    }
+    pipe.write_all(message.as_bytes())
+}
+
+#[cfg(not(windows))]
+pub fn serve(_on_message: impl FnMut(String)) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The daemon is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn send(_message: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The daemon is only supported on Windows",
+    ))
+}
@@ -0,0 +1,41 @@
+//! Sleeping and locking the computer
+
+use std::io;
+
+/// Put the computer to sleep, even when hibernation is enabled
+#[cfg(windows)]
+pub fn sleep() -> io::Result<()> {
+    use winapi::um::powrprof::SetSuspendState;
+
+    if unsafe { SetSuspendState(0, 0, 0) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Show the lock screen
+#[cfg(windows)]
+pub fn lock() -> io::Result<()> {
+    use winapi::um::winuser::LockWorkStation;
+
+    if unsafe { LockWorkStation() } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn sleep() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Sleeping is only supported on Windows",
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn lock() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Locking is only supported on Windows",
+    ))
+}
@@ -0,0 +1,154 @@
+//! Reading and writing values in the Windows registry
+
+use crate::win::{from_wide, to_wide};
+use std::io;
+use std::ptr;
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::winnt::{KEY_READ, REG_SZ};
+use winapi::um::winreg::{
+    RegCloseKey, RegDeleteKeyValueW, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, RegSetKeyValueW,
+    RRF_RT_REG_BINARY, RRF_RT_REG_EXPAND_SZ, RRF_RT_REG_SZ,
+};
+
+pub use winapi::um::winreg::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+/// Names of the direct subkeys of a key, empty if it can't be opened
+pub fn subkeys(root: HKEY, path: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    unsafe {
+        let mut key: HKEY = ptr::null_mut();
+        if RegOpenKeyExW(root, to_wide(path).as_ptr(), 0, KEY_READ, &mut key) as u32
+            != ERROR_SUCCESS
+        {
+            return names;
+        }
+        let mut buffer = [0u16; 256];
+        let mut index = 0;
+        loop {
+            let mut len = buffer.len() as DWORD;
+            let result = RegEnumKeyExW(
+                key,
+                index,
+                buffer.as_mut_ptr(),
+                &mut len,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            if result as u32 != ERROR_SUCCESS {
+                break;
+            }
+            names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+            index += 1;
+        }
+        RegCloseKey(key);
+    }
+    names
+}
+
+/// Read a string value, with environment variables expanded
+pub fn get_string(root: HKEY, path: &str, name: &str) -> Option<String> {
+    let path = to_wide(path);
+    let name = to_wide(name);
+    let flags = RRF_RT_REG_SZ | RRF_RT_REG_EXPAND_SZ;
+    unsafe {
+        let mut size: DWORD = 0;
+        if RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            flags,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut size,
+        ) as u32
+            != ERROR_SUCCESS
+        {
+            return None;
+        }
+        let mut buffer = vec![0u16; size as usize / 2 + 1];
+        let mut size = (buffer.len() * 2) as DWORD;
+        if RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            flags,
+            ptr::null_mut(),
+            buffer.as_mut_ptr() as *mut _,
+            &mut size,
+        ) as u32
+            != ERROR_SUCCESS
+        {
+            return None;
+        }
+        Some(from_wide(&buffer))
+    }
+}
+
+/// Read a binary value
+pub fn get_binary(root: HKEY, path: &str, name: &str) -> Option<Vec<u8>> {
+    let path = to_wide(path);
+    let name = to_wide(name);
+    unsafe {
+        let mut size: DWORD = 0;
+        if RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_BINARY,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut size,
+        ) as u32
+            != ERROR_SUCCESS
+        {
+            return None;
+        }
+        let mut buffer = vec![0u8; size as usize];
+        if RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_BINARY,
+            ptr::null_mut(),
+            buffer.as_mut_ptr() as *mut _,
+            &mut size,
+        ) as u32
+            != ERROR_SUCCESS
+        {
+            return None;
+        }
+        buffer.truncate(size as usize);
+        Some(buffer)
+    }
+}
+
+/// Write a string value, creating the key if it doesn't exist
+pub fn set_string(root: HKEY, path: &str, name: &str, value: &str) -> io::Result<()> {
+    let value = to_wide(value);
+    let result = unsafe {
+        RegSetKeyValueW(
+            root,
+            to_wide(path).as_ptr(),
+            to_wide(name).as_ptr(),
+            REG_SZ,
+            value.as_ptr() as *const _,
+            (value.len() * 2) as DWORD,
+        )
+    };
+    if result as u32 != ERROR_SUCCESS {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+pub fn delete_value(root: HKEY, path: &str, name: &str) -> io::Result<()> {
+    let result =
+        unsafe { RegDeleteKeyValueW(root, to_wide(path).as_ptr(), to_wide(name).as_ptr()) };
+    if result as u32 != ERROR_SUCCESS {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
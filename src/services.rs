@@ -0,0 +1,103 @@
+//! Listing Windows services and starting or stopping them with `net`
+
+use std::io;
+use std::process::{Command, Stdio};
+
+pub struct Service {
+    pub name: String,
+    pub display_name: String,
+    pub running: bool,
+}
+
+/// All Win32 services, running or not
+#[cfg(windows)]
+pub fn list() -> io::Result<Vec<Service>> {
+    use crate::win::from_wide_ptr;
+    use std::ptr;
+    use winapi::shared::winerror::ERROR_MORE_DATA;
+    use winapi::um::winnt::SERVICE_WIN32;
+    use winapi::um::winsvc::{
+        CloseServiceHandle, EnumServicesStatusExW, OpenSCManagerW, ENUM_SERVICE_STATUS_PROCESSW,
+        SC_ENUM_PROCESS_INFO, SC_MANAGER_ENUMERATE_SERVICE, SERVICE_RUNNING, SERVICE_STATE_ALL,
+    };
+
+    let mut services = Vec::new();
+    unsafe {
+        let manager = OpenSCManagerW(ptr::null(), ptr::null(), SC_MANAGER_ENUMERATE_SERVICE);
+        if manager.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Services are returned in chunks, continuing where the resume handle points
+        let mut buffer = vec![0u64; 8192];
+        let mut resume_handle = 0;
+        loop {
+            let (mut bytes_needed, mut returned) = (0, 0);
+            let finished = EnumServicesStatusExW(
+                manager,
+                SC_ENUM_PROCESS_INFO,
+                SERVICE_WIN32,
+                SERVICE_STATE_ALL,
+                buffer.as_mut_ptr() as *mut u8,
+                (buffer.len() * 8) as u32,
+                &mut bytes_needed,
+                &mut returned,
+                &mut resume_handle,
+                ptr::null(),
+            ) != 0;
+            if !finished
+                && io::Error::last_os_error().raw_os_error() != Some(ERROR_MORE_DATA as i32)
+            {
+                let error = io::Error::last_os_error();
+                CloseServiceHandle(manager);
+                return Err(error);
+            }
+            let entries = std::slice::from_raw_parts(
+                buffer.as_ptr() as *const ENUM_SERVICE_STATUS_PROCESSW,
+                returned as usize,
+            );
+            for entry in entries {
+                services.push(Service {
+                    name: from_wide_ptr(entry.lpServiceName),
+                    display_name: from_wide_ptr(entry.lpDisplayName),
+                    running: entry.ServiceStatusProcess.dwCurrentState == SERVICE_RUNNING,
+                });
+            }
+            if finished {
+                break;
+            }
+        }
+        CloseServiceHandle(manager);
+    }
+    Ok(services)
+}
+
+#[cfg(not(windows))]
+pub fn list() -> io::Result<Vec<Service>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Services are only supported on Windows",
+    ))
+}
+
+/// The `net` command lines that start, stop or restart a service and wait for it
+pub fn command_line(action: &str, name: &str) -> Option<String> {
+    match action {
+        "start" | "stop" => Some(format!("net {} \"{}\"", action, name)),
+        "restart" => Some(format!("net stop \"{}\" && net start \"{}\"", name, name)),
+        _ => None,
+    }
+}
+
+/// Run a command line from `command_line` without elevation, returning whether it succeeded
+pub fn control(command_line: &str) -> io::Result<bool> {
+    let mut command = Command::new("cmd");
+    command.arg("/c");
+    // cmd has its own quoting rules, so the command line is passed as it is
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::raw_arg(&mut command, command_line);
+    #[cfg(not(windows))]
+    command.arg(command_line);
+    let status = command.stdout(Stdio::null()).status()?;
+    Ok(status.success())
+}
@@ -0,0 +1,69 @@
+//! Reading the targets of `.lnk` shell shortcuts
+
+/// Whether the path is a `.lnk` shell shortcut
+pub fn is_shortcut(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".lnk")
+}
+
+/// Resolve the file a shortcut points to, `None` if it can't be read or has no file target
+#[cfg(windows)]
+pub fn resolve_target(path: &str) -> Option<String> {
+    use crate::win::{from_wide, init_com, to_wide};
+    use std::ptr;
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::minwindef::MAX_PATH;
+    use winapi::shared::winerror::S_OK;
+    use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+    use winapi::um::combaseapi::CoCreateInstance;
+    use winapi::um::objidl::IPersistFile;
+    use winapi::um::shobjidl_core::IShellLinkW;
+    use winapi::um::unknwnbase::IUnknown;
+    use winapi::Interface;
+
+    const CLSID_SHELL_LINK: GUID = GUID {
+        Data1: 0x0002_1401,
+        Data2: 0x0000,
+        Data3: 0x0000,
+        Data4: [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+    };
+    const STGM_READ: u32 = 0;
+
+    init_com();
+    unsafe {
+        let mut link: *mut IShellLinkW = ptr::null_mut();
+        let result = CoCreateInstance(
+            &CLSID_SHELL_LINK,
+            ptr::null_mut(),
+            CLSCTX_INPROC_SERVER,
+            &IShellLinkW::uuidof(),
+            &mut link as *mut *mut IShellLinkW as *mut _,
+        );
+        if result != S_OK {
+            return None;
+        }
+
+        let mut file: *mut IPersistFile = ptr::null_mut();
+        let mut target = None;
+        if (*(link as *mut IUnknown))
+            .QueryInterface(&IPersistFile::uuidof(), &mut file as *mut *mut _ as *mut _)
+            == S_OK
+        {
+            if (*file).Load(to_wide(path).as_ptr(), STGM_READ) == S_OK {
+                let mut buffer = [0u16; MAX_PATH];
+                if (*link).GetPath(buffer.as_mut_ptr(), buffer.len() as i32, ptr::null_mut(), 0)
+                    == S_OK
+                {
+                    target = Some(from_wide(&buffer)).filter(|target| !target.is_empty());
+                }
+            }
+            (*file).Release();
+        }
+        (*link).Release();
+        target
+    }
+}
+
+#[cfg(not(windows))]
+pub fn resolve_target(_path: &str) -> Option<String> {
+    None
+}
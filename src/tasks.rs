@@ -0,0 +1,61 @@
+//! Listing and terminating running processes with `tasklist` and `taskkill`
+
+use std::io;
+use std::process::Command;
+
+pub struct Task {
+    pub name: String,
+    pub pid: u32,
+    /// Memory usage as `tasklist` formats it, e.g. `123,456 K`
+    pub memory: String,
+}
+
+impl Task {
+    /// Memory usage in kilobytes, for sorting
+    pub fn memory_kb(&self) -> u64 {
+        self.memory
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .unwrap_or(0)
+    }
+}
+
+// `"chrome.exe","1234","Console","1","123,456 K"`
+fn parse_csv_line(line: &str) -> Vec<&str> {
+    line.trim()
+        .trim_start_matches('"')
+        .trim_end_matches('"')
+        .split("\",\"")
+        .collect()
+}
+
+/// Running processes, listed by `tasklist`
+pub fn list() -> io::Result<Vec<Task>> {
+    let output = Command::new("tasklist")
+        .args(["/fo", "csv", "/nh"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("tasklist failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields = parse_csv_line(line);
+            Some(Task {
+                name: fields.first()?.to_string(),
+                pid: fields.get(1)?.parse().ok()?,
+                memory: fields.get(4)?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Forcefully terminate a process with `taskkill`, returning whether it succeeded
+pub fn kill(pid: u32) -> io::Result<bool> {
+    let status = Command::new("taskkill")
+        .args(["/f", "/pid", &pid.to_string()])
+        .status()?;
+    Ok(status.success())
+}
@@ -0,0 +1,211 @@
+//! The daemon's notification area icon, whose menu also pauses the hotkeys
+
+use crate::hotkeys::Hotkey;
+use std::io;
+
+/// What was chosen from the menu of the icon
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum Command {
+    Show,
+    Reindex,
+    OpenConfig,
+}
+
+// How often `reload_hotkeys` is asked for changed hotkeys
+#[cfg(windows)]
+const RELOAD_INTERVAL_MS: u32 = 2000;
+
+/// Show the icon and register the hotkeys, then call `on_command` with what's chosen from the
+/// icon's menu and `on_hotkey` with the name of each hotkey that is pressed, until "Quit" is
+/// chosen. The hotkeys are replaced by the ones `reload_hotkeys` returns when they changed.
+#[cfg(windows)]
+pub fn run(
+    mut hotkeys: Vec<(String, Hotkey)>,
+    mut on_hotkey: impl FnMut(&str),
+    mut on_command: impl FnMut(Command),
+    mut reload_hotkeys: impl FnMut() -> Option<Vec<(String, Hotkey)>>,
+) -> io::Result<()> {
+    use crate::hotkeys;
+    use crate::notify::copy_wide;
+    use crate::win::to_wide;
+    use std::mem;
+    use std::ptr;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::{HWND, POINT};
+    use winapi::um::shellapi::{
+        Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+    };
+    use winapi::um::winuser::{
+        AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DestroyWindow,
+        DispatchMessageW, GetCursorPos, GetMessageW, LoadIconW, PostMessageW, RegisterClassW,
+        SetForegroundWindow, SetTimer, TrackPopupMenu, IDI_APPLICATION, MF_CHECKED, MF_SEPARATOR,
+        MF_STRING, MSG, TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON, WM_APP, WM_HOTKEY,
+        WM_LBUTTONUP, WM_NULL, WM_RBUTTONUP, WM_TIMER, WNDCLASSW,
+    };
+
+    // Sent by the icon, and posted back to the loop below for a click
+    const WM_TRAY: UINT = WM_APP + 1;
+    const WM_TRAY_CLICK: UINT = WM_APP + 2;
+
+    const ID_SHOW: usize = 1;
+    const ID_REINDEX: usize = 2;
+    const ID_OPEN_CONFIG: usize = 3;
+    const ID_PAUSE: usize = 4;
+    const ID_QUIT: usize = 5;
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        message: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if message == WM_TRAY {
+            let event = lparam as UINT;
+            if event == WM_LBUTTONUP || event == WM_RBUTTONUP {
+                PostMessageW(hwnd, WM_TRAY_CLICK, event as WPARAM, 0);
+            }
+            return 0;
+        }
+        DefWindowProcW(hwnd, message, wparam, lparam)
+    }
+
+    let class_name = to_wide("wlines_run_tray");
+    unsafe {
+        let mut class: WNDCLASSW = mem::zeroed();
+        class.lpfnWndProc = Some(window_proc);
+        class.lpszClassName = class_name.as_ptr();
+        if RegisterClassW(&class) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // A hidden top-level window rather than a message-only one, since the menu needs a window
+        // that can be brought to the front
+        let window = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        if window.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut data: NOTIFYICONDATAW = mem::zeroed();
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = window;
+        data.uID = 1;
+        data.uFlags = NIF_ICON | NIF_TIP | NIF_MESSAGE;
+        data.uCallbackMessage = WM_TRAY;
+        data.hIcon = LoadIconW(ptr::null_mut(), IDI_APPLICATION);
+        copy_wide(&mut data.szTip, "wlines_run");
+        if Shell_NotifyIconW(NIM_ADD, &mut data) == 0 {
+            let error = io::Error::last_os_error();
+            DestroyWindow(window);
+            return Err(error);
+        }
+
+        hotkeys::register(&hotkeys);
+        SetTimer(window, 1, RELOAD_INTERVAL_MS, None);
+        let mut paused = false;
+        let mut message: MSG = mem::zeroed();
+        while GetMessageW(&mut message, ptr::null_mut(), 0, 0) > 0 {
+            match message.message {
+                WM_HOTKEY => {
+                    if let Some((name, _)) = hotkeys.get(message.wParam) {
+                        on_hotkey(name);
+                    }
+                }
+                WM_TIMER => {
+                    if let Some(changed) = reload_hotkeys() {
+                        if !paused {
+                            hotkeys::unregister(&hotkeys);
+                            hotkeys::register(&changed);
+                        }
+                        hotkeys = changed;
+                    }
+                }
+                WM_TRAY_CLICK if message.wParam as UINT == WM_LBUTTONUP => {
+                    on_command(Command::Show)
+                }
+                WM_TRAY_CLICK => {
+                    let menu = CreatePopupMenu();
+                    let item = |id: usize, flags: UINT, text: &str| {
+                        AppendMenuW(menu, MF_STRING | flags, id, to_wide(text).as_ptr());
+                    };
+                    item(ID_SHOW, 0, "Show launcher");
+                    item(ID_REINDEX, 0, "Reindex now");
+                    item(ID_OPEN_CONFIG, 0, "Open config");
+                    if !hotkeys.is_empty() {
+                        item(
+                            ID_PAUSE,
+                            if paused { MF_CHECKED } else { 0 },
+                            "Pause hotkeys",
+                        );
+                    }
+                    AppendMenuW(menu, MF_SEPARATOR, 0, ptr::null());
+                    item(ID_QUIT, 0, "Quit");
+
+                    // Without being in front, the menu wouldn't close when clicking elsewhere
+                    let mut cursor: POINT = mem::zeroed();
+                    GetCursorPos(&mut cursor);
+                    SetForegroundWindow(window);
+                    let chosen = TrackPopupMenu(
+                        menu,
+                        TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON,
+                        cursor.x,
+                        cursor.y,
+                        0,
+                        window,
+                        ptr::null(),
+                    ) as usize;
+                    PostMessageW(window, WM_NULL, 0, 0);
+                    DestroyMenu(menu);
+
+                    match chosen {
+                        ID_SHOW => on_command(Command::Show),
+                        ID_REINDEX => on_command(Command::Reindex),
+                        ID_OPEN_CONFIG => on_command(Command::OpenConfig),
+                        ID_PAUSE => {
+                            if paused {
+                                hotkeys::register(&hotkeys);
+                            } else {
+                                hotkeys::unregister(&hotkeys);
+                            }
+                            paused = !paused;
+                        }
+                        ID_QUIT => break,
+                        _ => {}
+                    }
+                }
+                _ => {
+                    DispatchMessageW(&message);
+                }
+            }
+        }
+
+        hotkeys::unregister(&hotkeys);
+        Shell_NotifyIconW(NIM_DELETE, &mut data);
+        DestroyWindow(window);
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn run(
+    _hotkeys: Vec<(String, Hotkey)>,
+    _on_hotkey: impl FnMut(&str),
+    _on_command: impl FnMut(Command),
+    _reload_hotkeys: impl FnMut() -> Option<Vec<(String, Hotkey)>>,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The tray icon is only supported on Windows",
+    ))
+}
@@ -0,0 +1,74 @@
+//! Finding the uninstallers registered in "Apps & features"
+
+/// An entry from "Apps & features"
+pub struct Uninstaller {
+    pub name: String,
+    pub command_line: String,
+}
+
+/// Find the registered uninstaller of the program at `target`, whose title is `title`
+#[cfg(windows)]
+pub fn find(target: &str, title: &str) -> Option<Uninstaller> {
+    use crate::registry::{self, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+    use std::path::Path;
+
+    const ROOTS: &[&str] = &[
+        "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+        "Software\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    ];
+
+    let target = target.to_ascii_lowercase();
+    let name = Path::new(title)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(title);
+
+    for &hive in &[HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE] {
+        for root in ROOTS {
+            for subkey in registry::subkeys(hive, root) {
+                let key = format!("{}\\{}", root, subkey);
+                let value = |value_name| registry::get_string(hive, &key, value_name);
+                let (display_name, command_line) =
+                    match (value("DisplayName"), value("UninstallString")) {
+                        (Some(display_name), Some(command_line)) => (display_name, command_line),
+                        _ => continue,
+                    };
+
+                let install_location = value("InstallLocation")
+                    .map(|location| {
+                        location
+                            .trim_matches('"')
+                            .trim_end_matches('\\')
+                            .to_ascii_lowercase()
+                    })
+                    .filter(|location| !location.is_empty());
+                // DisplayIcon is usually the main executable, optionally followed by `,<icon index>`
+                let display_icon = value("DisplayIcon").map(|icon| {
+                    let icon = icon.trim_matches('"');
+                    icon.rsplitn(2, ',')
+                        .last()
+                        .unwrap_or(icon)
+                        .trim_matches('"')
+                        .to_ascii_lowercase()
+                });
+
+                let matches = display_name.eq_ignore_ascii_case(name)
+                    || display_icon.as_deref() == Some(target.as_str())
+                    || install_location
+                        .is_some_and(|location| target.starts_with(&(location + "\\")));
+                if matches {
+                    return Some(Uninstaller {
+                        name: display_name,
+                        command_line,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(not(windows))]
+pub fn find(_target: &str, _title: &str) -> Option<Uninstaller> {
+    None
+}
@@ -0,0 +1,169 @@
+//! Unit conversions typed into the menu, e.g. `=10 mi in km` or `=72 f to c`
+
+use crate::calc;
+
+#[derive(PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Time,
+    Data,
+    Speed,
+    Temperature,
+}
+
+// Names of a unit, what it measures and how many of the dimension's base unit it is
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (
+        &["mm", "millimeter", "millimeters"],
+        Dimension::Length,
+        0.001,
+    ),
+    (
+        &["cm", "centimeter", "centimeters"],
+        Dimension::Length,
+        0.01,
+    ),
+    (&["m", "meter", "meters"], Dimension::Length, 1.0),
+    (
+        &["km", "kilometer", "kilometers"],
+        Dimension::Length,
+        1000.0,
+    ),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    (&["nmi"], Dimension::Length, 1852.0),
+    (
+        &["mg", "milligram", "milligrams"],
+        Dimension::Mass,
+        0.000_001,
+    ),
+    (&["g", "gram", "grams"], Dimension::Mass, 0.001),
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.0),
+    (&["t", "tonne", "tonnes"], Dimension::Mass, 1000.0),
+    (
+        &["oz", "ounce", "ounces"],
+        Dimension::Mass,
+        0.028_349_523_125,
+    ),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        Dimension::Mass,
+        0.453_592_37,
+    ),
+    (&["st", "stone"], Dimension::Mass, 6.350_293_18),
+    (
+        &["ml", "milliliter", "milliliters"],
+        Dimension::Volume,
+        0.001,
+    ),
+    (&["l", "liter", "liters"], Dimension::Volume, 1.0),
+    (&["floz"], Dimension::Volume, 0.029_573_529_562_5),
+    (&["cup", "cups"], Dimension::Volume, 0.236_588_236_5),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473_176_473),
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946_352_946),
+    (
+        &["gal", "gallon", "gallons"],
+        Dimension::Volume,
+        3.785_411_784,
+    ),
+    (&["ms"], Dimension::Time, 0.001),
+    (&["s", "sec", "second", "seconds"], Dimension::Time, 1.0),
+    (&["min", "minute", "minutes"], Dimension::Time, 60.0),
+    (&["h", "hr", "hour", "hours"], Dimension::Time, 3600.0),
+    (&["d", "day", "days"], Dimension::Time, 86400.0),
+    (&["wk", "week", "weeks"], Dimension::Time, 604_800.0),
+    (&["b", "byte", "bytes"], Dimension::Data, 1.0),
+    (&["kb"], Dimension::Data, 1e3),
+    (&["mb"], Dimension::Data, 1e6),
+    (&["gb"], Dimension::Data, 1e9),
+    (&["tb"], Dimension::Data, 1e12),
+    (&["kib"], Dimension::Data, 1024.0),
+    (&["mib"], Dimension::Data, 1_048_576.0),
+    (&["gib"], Dimension::Data, 1_073_741_824.0),
+    (&["tib"], Dimension::Data, 1_099_511_627_776.0),
+    (&["m/s", "mps"], Dimension::Speed, 1.0),
+    (&["km/h", "kmh", "kph"], Dimension::Speed, 1.0 / 3.6),
+    (&["mph"], Dimension::Speed, 0.447_04),
+    (&["kn", "knot", "knots"], Dimension::Speed, 1852.0 / 3600.0),
+    (&["c", "celsius"], Dimension::Temperature, 1.0),
+    (&["f", "fahrenheit"], Dimension::Temperature, 1.0),
+    (&["k", "kelvin"], Dimension::Temperature, 1.0),
+];
+
+fn find_unit(name: &str) -> Option<&'static (&'static [&'static str], Dimension, f64)> {
+    let name = name.to_lowercase();
+    UNITS
+        .iter()
+        .find(|(names, _, _)| names.contains(&name.as_str()))
+}
+
+// Temperatures are offset, so they go through Celsius instead of being scaled
+fn to_celsius(value: f64, unit: &str) -> f64 {
+    match unit {
+        "f" => (value - 32.0) * 5.0 / 9.0,
+        "k" => value - 273.15,
+        _ => value,
+    }
+}
+
+fn from_celsius(value: f64, unit: &str) -> f64 {
+    match unit {
+        "f" => value * 9.0 / 5.0 + 32.0,
+        "k" => value + 273.15,
+        _ => value,
+    }
+}
+
+/// Convert input like `10 mi in km` or `(2 + 3) ft to cm`, returning the result with its unit
+pub fn convert(input: &str) -> Option<String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if words.len() < 4 {
+        return None;
+    }
+    let (to_name, keyword, from_name) = (
+        words[words.len() - 1],
+        words[words.len() - 2].to_lowercase(),
+        words[words.len() - 3],
+    );
+    if keyword != "in" && keyword != "to" {
+        return None;
+    }
+    let value = calc::evaluate(&words[..words.len() - 3].join(" "))?;
+
+    let (from_names, from_dimension, from_factor) = find_unit(from_name)?;
+    let (to_names, to_dimension, to_factor) = find_unit(to_name)?;
+    if from_dimension != to_dimension {
+        return None;
+    }
+    let result = if *from_dimension == Dimension::Temperature {
+        from_celsius(to_celsius(value, from_names[0]), to_names[0])
+    } else {
+        value * from_factor / to_factor
+    };
+    Some(format!("{} {}", calc::format_number(result), to_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(convert("10 mi in km").as_deref(), Some("16.09344 km"));
+        assert_eq!(convert("72 f in c").as_deref(), Some("22.2222222222 c"));
+        assert_eq!(convert("0 C to K").as_deref(), Some("273.15 K"));
+        assert_eq!(convert("12 in to cm").as_deref(), Some("30.48 cm"));
+        assert_eq!(convert("(1 + 1) GiB in MiB").as_deref(), Some("2048 MiB"));
+    }
+
+    #[test]
+    fn invalid_conversions() {
+        assert_eq!(convert("10 kg in km"), None);
+        assert_eq!(convert("10 parsecs in km"), None);
+        assert_eq!(convert("2 + 2"), None);
+    }
+}
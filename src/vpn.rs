@@ -0,0 +1,97 @@
+//! Listing VPN and dial-up connections and dialing them with `rasdial`
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+pub struct Connection {
+    pub name: String,
+    pub connected: bool,
+}
+
+// Entries are the sections of an INI file, e.g. `[Work VPN]`
+fn parse_phonebook(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter_map(|line| line.strip_prefix('[')?.strip_suffix(']'))
+        .map(String::from)
+        .collect()
+}
+
+// Connections are saved for the current user or for all users
+fn phonebooks() -> Vec<PathBuf> {
+    ["APPDATA", "ProgramData"]
+        .iter()
+        .filter_map(env::var_os)
+        .map(|dir| PathBuf::from(dir).join("Microsoft\\Network\\Connections\\Pbk\\rasphone.pbk"))
+        .collect()
+}
+
+/// Connections from the phonebooks, with whether they're connected
+pub fn list() -> io::Result<Vec<Connection>> {
+    let mut names: Vec<String> = Vec::new();
+    for path in phonebooks() {
+        // The phonebook is usually UTF-8, but may be in the ANSI code page
+        if let Ok(bytes) = fs::read(path) {
+            for name in parse_phonebook(&String::from_utf8_lossy(&bytes)) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+
+    // Without arguments rasdial prints the names of the active connections, one per line,
+    // between localized messages
+    let output = Command::new("rasdial")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let active: Vec<&str> = output.lines().map(str::trim).collect();
+    Ok(names
+        .into_iter()
+        .map(|name| Connection {
+            connected: active.contains(&name.as_str()),
+            name,
+        })
+        .collect())
+}
+
+/// Dial a connection with its saved credentials, returning whether it connected
+pub fn connect(name: &str) -> io::Result<bool> {
+    let status = Command::new("rasdial")
+        .arg(name)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+/// Show the Windows dialog for dialing a connection, which asks for missing credentials
+pub fn connect_with_dialog(name: &str) -> io::Result<()> {
+    Command::new("rasphone").args(["-d", name]).spawn()?;
+    Ok(())
+}
+
+pub fn disconnect(name: &str) -> io::Result<bool> {
+    let status = Command::new("rasdial")
+        .args([name, "/disconnect"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phonebook_entries() {
+        let phonebook = "[Work VPN]\r\nEncoding=1\r\nPBVersion=8\r\n\r\n[Home]\r\nType=2\r\n";
+        assert_eq!(parse_phonebook(phonebook), ["Work VPN", "Home"]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Listing wireless networks and connecting to them with `netsh wlan`
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process::{Command, Stdio};
+
+pub struct Network {
+    pub ssid: String,
+    /// Strongest signal of the network's access points, in percent
+    pub signal: u32,
+    pub authentication: String,
+}
+
+// The value of a `Name    : value` line
+fn value(line: &str) -> Option<&str> {
+    line.split_once(':').map(|(_, value)| value.trim())
+}
+
+// The field names are localized, except for `SSID` and the signal's `%`:
+//
+// SSID 1 : HomeNet
+//     Network type            : Infrastructure
+//     Authentication          : WPA2-Personal
+//     Encryption              : CCMP
+//     BSSID 1                 : 01:23:45:67:89:ab
+//          Signal             : 90%
+fn parse_networks(output: &str) -> Vec<Network> {
+    let mut networks: Vec<Network> = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("SSID ") {
+            networks.push(Network {
+                ssid: value(line).unwrap_or("").to_string(),
+                signal: 0,
+                authentication: String::new(),
+            });
+            continue;
+        }
+        let network = match networks.last_mut() {
+            Some(network) => network,
+            None => continue,
+        };
+        let value = match value(line) {
+            Some(value) => value,
+            None => continue,
+        };
+        if let Some(signal) = value.strip_suffix('%').and_then(|s| s.parse().ok()) {
+            network.signal = network.signal.max(signal);
+        } else if network.authentication.is_empty() && line.trim_start().starts_with("Auth") {
+            network.authentication = value.to_string();
+        }
+    }
+    // Hidden networks have no name to connect with
+    networks.retain(|network| !network.ssid.is_empty());
+    networks
+}
+
+// netsh expects quotes only around values, e.g. `name="Home Net"`, so the arguments are passed as
+// they are
+fn netsh(args: &str) -> io::Result<(bool, String)> {
+    let mut command = Command::new("netsh");
+    command.arg("wlan");
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::raw_arg(&mut command, args);
+    #[cfg(not(windows))]
+    command.args(args.split(' '));
+    let output = command.stdin(Stdio::null()).output()?;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ))
+}
+
+/// Visible networks, strongest first
+pub fn list() -> io::Result<Vec<Network>> {
+    let (_, output) = netsh("show networks mode=bssid")?;
+    let mut networks = parse_networks(&output);
+    networks.sort_by_key(|network| std::cmp::Reverse(network.signal));
+    Ok(networks)
+}
+
+// netsh has no way to escape quotes inside its quoted values
+fn check_ssid(ssid: &str) -> io::Result<()> {
+    if ssid.contains('"') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "netsh can't handle network names containing quotes",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a profile with the network's key has been saved, so it can connect without asking
+pub fn has_profile(ssid: &str) -> io::Result<bool> {
+    let (_, output) = netsh("show profiles")?;
+    Ok(output
+        .lines()
+        .any(|line| line.starts_with("    ") && line.contains(" : ") && value(line) == Some(ssid)))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// A profile as the WLAN API expects it, for an open network if there's no key
+fn profile_xml(network: &Network, key: &str) -> String {
+    let ssid = xml_escape(&network.ssid);
+    let security = if key.is_empty() {
+        String::from(
+            "<authEncryption><authentication>open</authentication><encryption>none</encryption>\
+             <useOneX>false</useOneX></authEncryption>",
+        )
+    } else {
+        let authentication = if network.authentication.contains("WPA3") {
+            "WPA3SAE"
+        } else if network.authentication.contains("WPA2") {
+            "WPA2PSK"
+        } else {
+            "WPAPSK"
+        };
+        format!(
+            "<authEncryption><authentication>{}</authentication><encryption>AES</encryption>\
+             <useOneX>false</useOneX></authEncryption>\
+             <sharedKey><keyType>passPhrase</keyType><protected>false</protected>\
+             <keyMaterial>{}</keyMaterial></sharedKey>",
+            authentication,
+            xml_escape(key)
+        )
+    };
+    format!(
+        "<?xml version=\"1.0\"?>\
+         <WLANProfile xmlns=\"http://www.microsoft.com/networking/WLAN/profile/v1\">\
+         <name>{}</name><SSIDConfig><SSID><name>{}</name></SSID></SSIDConfig>\
+         <connectionType>ESS</connectionType><connectionMode>auto</connectionMode>\
+         <MSM><security>{}</security></MSM></WLANProfile>",
+        ssid, ssid, security
+    )
+}
+
+/// Save a profile for the network with its key, or an empty key for an open network
+pub fn add_profile(network: &Network, key: &str) -> io::Result<bool> {
+    // The profile is named after the network, which `connect` couldn't pass back to netsh
+    check_ssid(&network.ssid)?;
+    let path = env::temp_dir().join("wlines_run_wifi.xml");
+    fs::write(&path, profile_xml(network, key))?;
+    let result = netsh(&format!(
+        "add profile filename=\"{}\" user=current",
+        path.display()
+    ));
+    // The file holds the key in plain text
+    let _ = fs::remove_file(&path);
+    Ok(result?.0)
+}
+
+/// Connect with the network's saved profile, returning whether the connection was started
+pub fn connect(ssid: &str) -> io::Result<bool> {
+    check_ssid(ssid)?;
+    Ok(netsh(&format!("connect name=\"{}\" ssid=\"{}\"", ssid, ssid))?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_networks() {
+        let output = "\
+Interface name : Wi-Fi
+There are 2 networks currently visible.
+
+SSID 1 : HomeNet
+    Network type            : Infrastructure
+    Authentication          : WPA2-Personal
+    Encryption              : CCMP
+    BSSID 1                 : 01:23:45:67:89:ab
+         Signal             : 62%
+    BSSID 2                 : 01:23:45:67:89:ac
+         Signal             : 90%
+
+SSID 2 :
+    Network type            : Infrastructure
+    Authentication          : Open
+";
+        let networks = parse_networks(output);
+        assert_eq!(networks.len(), 1);
+        assert_eq!(networks[0].ssid, "HomeNet");
+        assert_eq!(networks[0].signal, 90);
+        assert_eq!(networks[0].authentication, "WPA2-Personal");
+    }
+}
@@ -0,0 +1,40 @@
+//! Small helpers shared by the Windows API wrappers
+
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use winapi::um::combaseapi::CoInitializeEx;
+use winapi::um::objbase::{COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE};
+
+/// NUL-terminated UTF-16 string for passing to the API
+pub fn to_wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(Some(0)).collect()
+}
+
+/// String from a NUL-terminated UTF-16 buffer
+pub fn from_wide(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+/// String from a NUL-terminated UTF-16 pointer returned by the API
+///
+/// # Safety
+/// The pointer must point to a NUL-terminated string.
+pub unsafe fn from_wide_ptr(ptr: *const u16) -> String {
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+    }
+    String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len))
+}
+
+/// Initialize COM for the current thread, which the shell APIs expect
+pub fn init_com() {
+    unsafe {
+        CoInitializeEx(
+            ptr::null_mut(),
+            COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE,
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Searching the winget catalog
+
+use std::io;
+use std::process::{Command, Stdio};
+
+pub struct Package {
+    pub name: String,
+    pub id: String,
+    pub version: String,
+}
+
+// Column offsets are in characters, since names may contain any Unicode
+fn column(chars: &[char], start: usize, end: usize) -> String {
+    chars[start.min(chars.len())..end.min(chars.len())]
+        .iter()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// winget prints a table whose columns are found from the header above the `---` line:
+// `Name   Id   Version   Match   Source`
+fn parse_table(output: &str) -> Vec<Package> {
+    // Progress spinners are drawn over with carriage returns
+    let lines: Vec<&str> = output
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or(line))
+        .collect();
+    let separator = match lines
+        .iter()
+        .position(|line| line.starts_with("---") && line.trim().chars().all(|c| c == '-'))
+    {
+        Some(separator) if separator > 0 => separator,
+        _ => return Vec::new(),
+    };
+
+    // The header is localized, but its columns are always in this order
+    let header: Vec<char> = lines[separator - 1].chars().collect();
+    let mut starts = Vec::new();
+    for i in 0..header.len() {
+        if header[i] != ' ' && (i == 0 || header[i - 1] == ' ') {
+            starts.push(i);
+        }
+    }
+    if starts.len() < 3 {
+        return Vec::new();
+    }
+    let (id_start, version_start) = (starts[1], starts[2]);
+    let version_end = starts.get(3).copied().unwrap_or(usize::MAX);
+
+    lines[separator + 1..]
+        .iter()
+        .filter_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let package = Package {
+                name: column(&chars, 0, id_start),
+                id: column(&chars, id_start, version_start),
+                version: column(&chars, version_start, version_end),
+            };
+            if package.id.is_empty() || package.id.contains(' ') {
+                return None;
+            }
+            Some(package)
+        })
+        .collect()
+}
+
+/// Packages in the winget catalog matching the query
+pub fn search(query: &str) -> io::Result<Vec<Package>> {
+    let output = Command::new("winget")
+        .args(["search", "--accept-source-agreements", query])
+        .stdin(Stdio::null())
+        .output()?;
+    Ok(parse_table(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_results() {
+        let output = "\r   - \r\
+Name             Id                      Version  Match        Source\n\
+---------------------------------------------------------------------\n\
+Mozilla Firefox  Mozilla.Firefox         128.0.3  Tag: browser winget\n\
+Firefox Nightly  Mozilla.Firefox.Nightly 130.0a1               winget\n";
+        let packages = parse_table(output);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "Mozilla Firefox");
+        assert_eq!(packages[0].id, "Mozilla.Firefox");
+        assert_eq!(packages[0].version, "128.0.3");
+        assert_eq!(packages[1].id, "Mozilla.Firefox.Nightly");
+    }
+}